@@ -0,0 +1,885 @@
+use crate::custom_http;
+use crate::local_command;
+use crate::mock;
+use crate::retry::ProviderError;
+use crate::settings::ProviderConfig;
+use crate::usage::TokenUsage;
+use futures_util::StreamExt;
+use llm::chat::{ChatMessage, ChatRole};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Upper bound for model catalogue requests, which should be quick
+const LIST_MODELS_TIMEOUT_SECS: u64 = 10;
+
+/// Complete response text, with token usage if the backend reported it
+#[derive(Debug, Clone)]
+pub struct ChatResponse {
+    pub text: String,
+    pub usage: Option<TokenUsage>,
+}
+
+/// Wire protocol spoken by a provider's HTTP API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKind {
+    OpenAICompatible,
+    Anthropic,
+    Google,
+    Ollama,
+}
+
+impl ApiKind {
+    pub fn for_provider(provider: &str) -> Option<Self> {
+        match provider.to_lowercase().as_str() {
+            "openai" | "deepseek" | "xai" | "groq" => Some(Self::OpenAICompatible),
+            "anthropic" => Some(Self::Anthropic),
+            "google" => Some(Self::Google),
+            "ollama" => Some(Self::Ollama),
+            _ => None,
+        }
+    }
+}
+
+/// Default API root for providers we can talk to directly
+pub fn default_base_url(provider: &str) -> Option<&'static str> {
+    match provider.to_lowercase().as_str() {
+        "openai" => Some("https://api.openai.com/v1"),
+        "deepseek" => Some("https://api.deepseek.com/v1"),
+        "xai" => Some("https://api.x.ai/v1"),
+        "groq" => Some("https://api.groq.com/openai/v1"),
+        "anthropic" => Some("https://api.anthropic.com/v1"),
+        "google" => Some("https://generativelanguage.googleapis.com/v1beta"),
+        "ollama" => Some("http://localhost:11434"),
+        _ => None,
+    }
+}
+
+/// Whether the provider is implemented in Quillbert rather than by the llm crate
+pub fn is_builtin(provider: &str) -> bool {
+    mock::is_mock(provider)
+        || custom_http::is_custom_http(provider)
+        || local_command::is_command(provider)
+}
+
+/// Whether responses from this provider can be streamed
+pub fn supports_streaming(provider: &str) -> bool {
+    ApiKind::for_provider(provider).is_some()
+}
+
+/// Whether the backend accepts a separate system prompt. Phind ignores it and
+/// custom HTTP endpoints and local commands only take a prompt, so system
+/// instructions for them are prepended to the user message instead.
+pub fn supports_system_prompt(provider: &str) -> bool {
+    !provider.eq_ignore_ascii_case("phind")
+        && !custom_http::is_custom_http(provider)
+        && !local_command::is_command(provider)
+}
+
+/// Flatten a conversation into one prompt, for backends that take a single
+/// text input. A lone message is passed on as it is.
+pub fn flatten_conversation(messages: &[ChatMessage]) -> String {
+    if let [message] = messages {
+        return message.content.clone();
+    }
+
+    messages
+        .iter()
+        .map(|m| {
+            let role = if matches!(m.role, ChatRole::Assistant) {
+                "Assistant"
+            } else {
+                "User"
+            };
+            format!("{}: {}", role, m.content)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// API root for a config, preferring its custom base URL
+fn api_base_url(config: &ProviderConfig) -> Result<&str, BoxError> {
+    let base_url = config
+        .base_url
+        .as_deref()
+        .or_else(|| default_base_url(&config.provider))
+        .ok_or_else(|| format!("No API endpoint known for provider '{}'", config.provider))?;
+    Ok(base_url.trim_end_matches('/'))
+}
+
+const OPENAI_HOST: &str = "api.openai.com";
+
+/// Whether the config talks to OpenAI itself, which reports the token usage of
+/// a stream when asked to. Compatible gateways such as vLLM, LM Studio or
+/// Azure may reject the `stream_options` field, so it is only sent to OpenAI.
+fn streams_usage(config: &ProviderConfig) -> bool {
+    api_base_url(config)
+        .ok()
+        .and_then(|base_url| reqwest::Url::parse(base_url).ok())
+        .is_some_and(|url| url.host_str() == Some(OPENAI_HOST))
+}
+
+/// Sampling settings beyond temperature and max tokens, as named in `ProviderConfig`
+const TOP_P: &str = "top_p";
+const TOP_K: &str = "top_k";
+const STOP: &str = "stop";
+const SEED: &str = "seed";
+const REASONING_EFFORT: &str = "reasoning_effort";
+const THINKING_BUDGET: &str = "thinking_budget";
+
+/// Extra sampling settings each API accepts when we talk to it directly
+fn supported_sampling_params(kind: ApiKind) -> &'static [&'static str] {
+    match kind {
+        ApiKind::OpenAICompatible => &[TOP_P, STOP, SEED, REASONING_EFFORT],
+        ApiKind::Anthropic => &[TOP_P, TOP_K, STOP, THINKING_BUDGET],
+        ApiKind::Google => &[TOP_P, TOP_K, STOP, SEED, THINKING_BUDGET],
+        ApiKind::Ollama => &[TOP_P, TOP_K, STOP, SEED],
+    }
+}
+
+/// Extra sampling settings that are set on a config
+fn extra_sampling_params(config: &ProviderConfig) -> Vec<&'static str> {
+    [
+        (TOP_P, config.top_p.is_some()),
+        (TOP_K, config.top_k.is_some()),
+        (STOP, !config.stop.is_empty()),
+        (SEED, config.seed.is_some()),
+        (REASONING_EFFORT, config.reasoning_effort.is_some()),
+        (THINKING_BUDGET, config.thinking_budget.is_some()),
+    ]
+    .into_iter()
+    .filter_map(|(name, set)| set.then_some(name))
+    .collect()
+}
+
+/// Whether the config sets options the `llm` crate can't forward, such as
+/// stop sequences, a proxy or extra headers, so requests have to go to the
+/// API directly
+pub fn needs_direct_request(config: &ProviderConfig) -> bool {
+    uses_custom_client(config)
+        || extra_sampling_params(config)
+            .iter()
+            .any(|name| !matches!(*name, TOP_P | TOP_K))
+}
+
+/// Whether requests for the config need their own HTTP client
+pub fn uses_custom_client(config: &ProviderConfig) -> bool {
+    config.proxy_url.is_some() || !config.headers.is_empty()
+}
+
+/// HTTP client that sends every request for a config through its proxy,
+/// with its extra headers
+pub fn build_client(config: &ProviderConfig) -> Result<reqwest::Client, BoxError> {
+    let mut builder = reqwest::Client::builder();
+
+    if let Some(proxy_url) = &config.proxy_url {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|e| format!("Invalid proxy URL '{}': {}", proxy_url, e))?
+            .no_proxy(reqwest::NoProxy::from_string(&config.no_proxy.join(",")));
+        builder = builder.proxy(proxy);
+    }
+
+    let mut headers = reqwest::header::HeaderMap::new();
+    for (name, value) in &config.headers {
+        let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| format!("Invalid header name '{}': {}", name, e))?;
+        let value = reqwest::header::HeaderValue::from_str(value)
+            .map_err(|e| format!("Invalid value for header '{}': {}", name, e))?;
+        headers.insert(name, value);
+    }
+
+    Ok(builder.default_headers(headers).build()?)
+}
+
+/// Extra sampling settings that are set on a config but have no effect with
+/// its provider
+pub fn ignored_sampling_params(config: &ProviderConfig) -> Vec<&'static str> {
+    let kind = ApiKind::for_provider(&config.provider);
+    let supported = kind.map(supported_sampling_params).unwrap_or_default();
+    let anthropic_thinking = kind == Some(ApiKind::Anthropic) && config.thinking_budget.is_some();
+    let openai_reasoning =
+        kind == Some(ApiKind::OpenAICompatible) && config.reasoning_effort.is_some();
+
+    extra_sampling_params(config)
+        .into_iter()
+        .filter(|name| {
+            // Sampling settings are left out of requests while the model thinks
+            !supported.contains(name)
+                || (anthropic_thinking && matches!(*name, TOP_P | TOP_K))
+                || (openai_reasoning && *name == TOP_P)
+        })
+        .collect()
+}
+
+/// Reasoning efforts OpenAI-style reasoning models accept
+const REASONING_EFFORTS: [&str; 3] = ["low", "medium", "high"];
+
+/// Check the reasoning effort is one the API accepts
+pub fn validate_reasoning_effort(config: &ProviderConfig) -> Result<(), BoxError> {
+    match config.reasoning_effort.as_deref() {
+        Some(effort) if !REASONING_EFFORTS.contains(&effort) => Err(format!(
+            "Unknown reasoning effort '{}', use low, medium or high",
+            effort
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Smallest thinking budget Anthropic accepts
+const ANTHROPIC_MIN_THINKING_BUDGET: u32 = 1024;
+
+/// Check the thinking budget against Anthropic's rules: it has a minimum, and
+/// as thinking counts towards max tokens, it must leave room for the answer
+pub fn validate_thinking_budget(config: &ProviderConfig) -> Result<(), BoxError> {
+    let Some(budget) = config.thinking_budget else {
+        return Ok(());
+    };
+    if ApiKind::for_provider(&config.provider) != Some(ApiKind::Anthropic) {
+        return Ok(());
+    }
+
+    if budget < ANTHROPIC_MIN_THINKING_BUDGET {
+        return Err(format!(
+            "The thinking budget must be at least {} tokens",
+            ANTHROPIC_MIN_THINKING_BUDGET
+        )
+        .into());
+    }
+    if budget >= config.max_tokens {
+        return Err(format!(
+            "The thinking budget ({}) must be lower than max tokens ({})",
+            budget, config.max_tokens
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Copy the optional fields that are set into a JSON object
+fn set_optional(target: &mut Value, fields: Vec<(&str, Option<Value>)>) {
+    for (key, value) in fields {
+        if let Some(value) = value {
+            target[key] = value;
+        }
+    }
+}
+
+fn role_name(role: &ChatRole) -> &'static str {
+    match role {
+        ChatRole::User => "user",
+        ChatRole::Assistant => "assistant",
+    }
+}
+
+/// Build the streaming chat request for the provider's API
+fn build_stream_request(
+    client: &reqwest::Client,
+    kind: ApiKind,
+    config: &ProviderConfig,
+    system: Option<&str>,
+    messages: &[ChatMessage],
+) -> Result<reqwest::RequestBuilder, BoxError> {
+    let base_url = api_base_url(config)?;
+
+    let mut chat_messages: Vec<Value> = messages
+        .iter()
+        .map(|m| json!({ "role": role_name(&m.role), "content": m.content }))
+        .collect();
+
+    // OpenAI-compatible APIs and Ollama take the system prompt as a leading message
+    if let (Some(system), ApiKind::OpenAICompatible | ApiKind::Ollama) = (system, kind) {
+        chat_messages.insert(0, json!({ "role": "system", "content": system }));
+    }
+
+    let stop = (!config.stop.is_empty()).then(|| json!(config.stop));
+
+    let request = match kind {
+        ApiKind::OpenAICompatible => {
+            let mut body = json!({
+                "model": config.model,
+                "messages": chat_messages,
+                "temperature": config.temperature,
+                "max_tokens": config.max_tokens,
+                "stream": true,
+            });
+            if streams_usage(config) {
+                body["stream_options"] = json!({ "include_usage": true });
+            }
+            set_optional(
+                &mut body,
+                vec![
+                    ("top_p", config.top_p.map(|v| json!(v))),
+                    ("stop", stop),
+                    ("seed", config.seed.map(|v| json!(v))),
+                    (
+                        "reasoning_effort",
+                        config.reasoning_effort.as_ref().map(|v| json!(v)),
+                    ),
+                ],
+            );
+            if config.reasoning_effort.is_some() {
+                // Reasoning models reject sampling settings and take
+                // max_completion_tokens instead of max_tokens
+                if let Some(body) = body.as_object_mut() {
+                    for key in ["temperature", "top_p"] {
+                        body.remove(key);
+                    }
+                    if let Some(max_tokens) = body.remove("max_tokens") {
+                        body.insert("max_completion_tokens".to_string(), max_tokens);
+                    }
+                }
+            }
+            client
+                .post(format!("{}/chat/completions", base_url))
+                .bearer_auth(&config.api_key)
+                .json(&body)
+        }
+        ApiKind::Anthropic => {
+            let mut body = json!({
+                "model": config.model,
+                "messages": chat_messages,
+                "temperature": config.temperature,
+                "max_tokens": config.max_tokens,
+                "stream": true,
+            });
+            if let Some(system) = system {
+                body["system"] = json!(system);
+            }
+            set_optional(
+                &mut body,
+                vec![
+                    ("top_p", config.top_p.map(|v| json!(v))),
+                    ("top_k", config.top_k.map(|v| json!(v))),
+                    ("stop_sequences", stop),
+                ],
+            );
+            if let Some(budget) = config.thinking_budget {
+                // Extended thinking only works with the default sampling settings
+                body["thinking"] = json!({ "type": "enabled", "budget_tokens": budget });
+                if let Some(body) = body.as_object_mut() {
+                    for key in ["temperature", "top_p", "top_k"] {
+                        body.remove(key);
+                    }
+                }
+            }
+            client
+                .post(format!("{}/messages", base_url))
+                .header("x-api-key", &config.api_key)
+                .header("anthropic-version", "2023-06-01")
+                .json(&body)
+        }
+        ApiKind::Google => {
+            let contents: Vec<Value> = messages
+                .iter()
+                .map(|m| {
+                    let role = match m.role {
+                        ChatRole::User => "user",
+                        ChatRole::Assistant => "model",
+                    };
+                    json!({ "role": role, "parts": [{ "text": m.content }] })
+                })
+                .collect();
+            let mut generation_config = json!({
+                "temperature": config.temperature,
+                "maxOutputTokens": config.max_tokens,
+            });
+            set_optional(
+                &mut generation_config,
+                vec![
+                    ("topP", config.top_p.map(|v| json!(v))),
+                    ("topK", config.top_k.map(|v| json!(v))),
+                    ("stopSequences", stop),
+                    ("seed", config.seed.map(|v| json!(v))),
+                    (
+                        "thinkingConfig",
+                        config
+                            .thinking_budget
+                            .map(|budget| json!({ "thinkingBudget": budget })),
+                    ),
+                ],
+            );
+            let mut body = json!({
+                "contents": contents,
+                "generationConfig": generation_config,
+            });
+            if let Some(system) = system {
+                body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+            }
+            client
+                .post(format!(
+                    "{}/models/{}:streamGenerateContent",
+                    base_url, config.model
+                ))
+                .query(&[("alt", "sse")])
+                // Not in the query string, as request errors quote the URL
+                .header("x-goog-api-key", &config.api_key)
+                .json(&body)
+        }
+        ApiKind::Ollama => {
+            let mut options = json!({
+                "temperature": config.temperature,
+                "num_predict": config.max_tokens,
+            });
+            set_optional(
+                &mut options,
+                vec![
+                    ("top_p", config.top_p.map(|v| json!(v))),
+                    ("top_k", config.top_k.map(|v| json!(v))),
+                    ("stop", stop),
+                    ("seed", config.seed.map(|v| json!(v))),
+                ],
+            );
+            client.post(format!("{}/api/chat", base_url)).json(&json!({
+                "model": config.model,
+                "messages": chat_messages,
+                "stream": true,
+                "options": options,
+            }))
+        }
+    };
+
+    Ok(request)
+}
+
+/// Extract the text delta from a single streamed line, if it carries one,
+/// and pick up any token usage it reports. An error event fails the stream.
+fn parse_stream_line(
+    kind: ApiKind,
+    line: &str,
+    usage: &mut Option<TokenUsage>,
+) -> Result<Option<String>, BoxError> {
+    let Some(value) = stream_event(kind, line) else {
+        return Ok(None);
+    };
+    if let Some(message) = stream_error(kind, &value) {
+        return Err(format!("The response stream failed: {}", message).into());
+    }
+    read_stream_usage(kind, &value, usage);
+    Ok(stream_text(kind, &value).filter(|text| !text.is_empty()))
+}
+
+/// The JSON event of a streamed line, skipping blank lines, SSE comments and
+/// the `[DONE]` marker
+fn stream_event(kind: ApiKind, line: &str) -> Option<Value> {
+    let payload = match kind {
+        // Ollama streams newline-delimited JSON, everyone else uses server-sent events
+        ApiKind::Ollama => line,
+        _ => line.strip_prefix("data:")?.trim_start(),
+    };
+
+    if payload.is_empty() || payload == "[DONE]" {
+        return None;
+    }
+    serde_json::from_str(payload).ok()
+}
+
+/// Message of an error reported in the middle of a stream, such as
+/// Anthropic's `overloaded_error`
+fn stream_error(kind: ApiKind, value: &Value) -> Option<String> {
+    let error = match kind {
+        ApiKind::Anthropic if value["type"] == "error" => &value["error"],
+        ApiKind::Anthropic => return None,
+        _ => value.get("error").filter(|error| !error.is_null())?,
+    };
+    let message = error
+        .as_str()
+        .or_else(|| error["message"].as_str())
+        .unwrap_or("unknown error");
+    Some(match error["type"].as_str() {
+        Some(error_type) => format!("{} ({})", message, error_type),
+        None => message.to_string(),
+    })
+}
+
+/// Text delta carried by a streamed event
+fn stream_text(kind: ApiKind, value: &Value) -> Option<String> {
+    let text = match kind {
+        ApiKind::OpenAICompatible => value
+            .pointer("/choices/0/delta/content")?
+            .as_str()?
+            .to_string(),
+        ApiKind::Anthropic => {
+            if value["type"] != "content_block_delta" {
+                return None;
+            }
+            value.pointer("/delta/text")?.as_str()?.to_string()
+        }
+        ApiKind::Google => value
+            .pointer("/candidates/0/content/parts")?
+            .as_array()?
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect(),
+        ApiKind::Ollama => value.pointer("/message/content")?.as_str()?.to_string(),
+    };
+    Some(text)
+}
+
+/// Update the token usage from a streamed event that reports it. Counts are
+/// cumulative, so later events replace earlier ones.
+fn read_stream_usage(kind: ApiKind, value: &Value, usage: &mut Option<TokenUsage>) {
+    let count = |pointer: &str| value.pointer(pointer).and_then(Value::as_u64);
+    let (input, output) = match kind {
+        ApiKind::OpenAICompatible => (
+            count("/usage/prompt_tokens"),
+            count("/usage/completion_tokens"),
+        ),
+        ApiKind::Anthropic => (
+            count("/message/usage/input_tokens"),
+            count("/usage/output_tokens"),
+        ),
+        ApiKind::Google => (
+            count("/usageMetadata/promptTokenCount"),
+            count("/usageMetadata/candidatesTokenCount"),
+        ),
+        ApiKind::Ollama => (count("/prompt_eval_count"), count("/eval_count")),
+    };
+
+    if input.is_none() && output.is_none() {
+        return;
+    }
+    let usage = usage.get_or_insert_with(TokenUsage::default);
+    if let Some(input) = input {
+        usage.input_tokens = input;
+    }
+    if let Some(output) = output {
+        usage.output_tokens = output;
+    }
+}
+
+/// Stream a chat completion, calling `on_chunk` for every text delta.
+/// Returns the full response once the stream ends.
+pub async fn stream_chat<F>(
+    client: &reqwest::Client,
+    config: &ProviderConfig,
+    system: Option<&str>,
+    messages: &[ChatMessage],
+    mut on_chunk: F,
+) -> Result<ChatResponse, BoxError>
+where
+    F: FnMut(&str) + Send,
+{
+    let kind = ApiKind::for_provider(&config.provider)
+        .ok_or_else(|| format!("Streaming is not supported for '{}'", config.provider))?;
+
+    let response = build_stream_request(client, kind, config, system, messages)?
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let headers = response.headers().clone();
+        let body = response.text().await.unwrap_or_default();
+        return Err(ProviderError::from_response(&config.provider, status, &headers, &body).into());
+    }
+
+    let mut full_text = String::new();
+    let mut usage = None;
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut stream = response.bytes_stream();
+
+    while let Some(bytes) = stream.next().await {
+        buffer.extend_from_slice(&bytes?);
+
+        // Only handle complete lines, a chunk may end mid-line
+        while let Some(newline) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(text) = parse_stream_line(kind, line.trim(), &mut usage)? {
+                on_chunk(&text);
+                full_text.push_str(&text);
+            }
+        }
+    }
+
+    // Flush a trailing line without a newline terminator
+    let line = String::from_utf8_lossy(&buffer);
+    if let Some(text) = parse_stream_line(kind, line.trim(), &mut usage)? {
+        on_chunk(&text);
+        full_text.push_str(&text);
+    }
+
+    Ok(ChatResponse {
+        text: full_text,
+        usage,
+    })
+}
+
+/// A model offered by a provider, with whatever metadata its catalogue exposes
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owned_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u64>,
+    /// Download size, reported by local runtimes such as Ollama
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+}
+
+impl ModelInfo {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            display_name: None,
+            owned_by: None,
+            context_window: None,
+            size_bytes: None,
+        }
+    }
+}
+
+/// Fetch the model catalogue from the provider's API
+pub async fn list_models(
+    client: &reqwest::Client,
+    config: &ProviderConfig,
+) -> Result<Vec<ModelInfo>, BoxError> {
+    let kind = ApiKind::for_provider(&config.provider)
+        .ok_or_else(|| format!("Listing models is not supported for '{}'", config.provider))?;
+    let base_url = api_base_url(config)?;
+
+    let request = match kind {
+        ApiKind::OpenAICompatible => client
+            .get(format!("{}/models", base_url))
+            .bearer_auth(&config.api_key),
+        ApiKind::Anthropic => client
+            .get(format!("{}/models", base_url))
+            .query(&[("limit", "1000")])
+            .header("x-api-key", &config.api_key)
+            .header("anthropic-version", "2023-06-01"),
+        ApiKind::Google => client
+            .get(format!("{}/models", base_url))
+            .query(&[("pageSize", "1000")])
+            .header("x-goog-api-key", &config.api_key),
+        ApiKind::Ollama => client.get(format!("{}/api/tags", base_url)),
+    };
+
+    let response = request
+        .timeout(Duration::from_secs(LIST_MODELS_TIMEOUT_SECS))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let headers = response.headers().clone();
+        let body = response.text().await.unwrap_or_default();
+        return Err(ProviderError::from_response(&config.provider, status, &headers, &body).into());
+    }
+
+    let body: Value = response.json().await?;
+    let entries = match kind {
+        ApiKind::OpenAICompatible | ApiKind::Anthropic => &body["data"],
+        ApiKind::Google | ApiKind::Ollama => &body["models"],
+    };
+    let entries = entries
+        .as_array()
+        .ok_or_else(|| format!("Unexpected model list from '{}'", config.provider))?;
+
+    let mut models: Vec<ModelInfo> = entries
+        .iter()
+        .filter_map(|entry| parse_model_entry(kind, entry))
+        .collect();
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(models)
+}
+
+fn parse_model_entry(kind: ApiKind, entry: &Value) -> Option<ModelInfo> {
+    let text = |key: &str| entry[key].as_str().map(str::to_string);
+
+    let model = match kind {
+        ApiKind::OpenAICompatible => ModelInfo {
+            owned_by: text("owned_by"),
+            context_window: entry["context_window"].as_u64(),
+            ..ModelInfo::new(entry["id"].as_str()?)
+        },
+        ApiKind::Anthropic => ModelInfo {
+            display_name: text("display_name"),
+            ..ModelInfo::new(entry["id"].as_str()?)
+        },
+        ApiKind::Google => {
+            // Only models that can generate text are usable for prompts
+            if let Some(methods) = entry["supportedGenerationMethods"].as_array() {
+                if !methods.iter().any(|m| m == "generateContent") {
+                    return None;
+                }
+            }
+            let name = entry["name"].as_str()?;
+            ModelInfo {
+                display_name: text("displayName"),
+                context_window: entry["inputTokenLimit"].as_u64(),
+                ..ModelInfo::new(name.strip_prefix("models/").unwrap_or(name))
+            }
+        }
+        ApiKind::Ollama => ModelInfo {
+            size_bytes: entry["size"].as_u64(),
+            ..ModelInfo::new(entry["name"].as_str()?)
+        },
+    };
+
+    Some(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// JSON body of the streaming request for a config with no messages
+    fn request_body(config: &ProviderConfig) -> Value {
+        let kind = ApiKind::for_provider(&config.provider).unwrap();
+        let request = build_stream_request(&reqwest::Client::new(), kind, config, None, &[])
+            .unwrap()
+            .build()
+            .unwrap();
+        serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap()
+    }
+
+    fn openai(reasoning_effort: Option<&str>) -> ProviderConfig {
+        ProviderConfig {
+            provider: "openai".to_string(),
+            model: "o4-mini".to_string(),
+            temperature: 0.7,
+            max_tokens: 2048,
+            top_p: Some(0.5),
+            reasoning_effort: reasoning_effort.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sends_reasoning_models_max_completion_tokens_only() {
+        let body = request_body(&openai(Some("high")));
+        assert_eq!(body["reasoning_effort"], "high");
+        assert_eq!(body["max_completion_tokens"], 2048);
+        for key in ["max_tokens", "temperature", "top_p"] {
+            assert!(body.get(key).is_none(), "{} was sent", key);
+        }
+        assert_eq!(ignored_sampling_params(&openai(Some("high"))), [TOP_P]);
+
+        let body = request_body(&openai(None));
+        assert_eq!(body["max_tokens"], 2048);
+        assert_eq!(body["top_p"], 0.5);
+        assert!(body.get("max_completion_tokens").is_none());
+        assert!(ignored_sampling_params(&openai(None)).is_empty());
+    }
+
+    #[test]
+    fn accepts_known_reasoning_efforts_only() {
+        assert!(validate_reasoning_effort(&openai(None)).is_ok());
+        assert!(validate_reasoning_effort(&openai(Some("medium"))).is_ok());
+        assert!(validate_reasoning_effort(&openai(Some("max"))).is_err());
+    }
+
+    /// Text and token counts of a whole stream, as `stream_chat` collects them
+    fn parse(kind: ApiKind, lines: &[&str]) -> Result<(String, Option<(u64, u64)>), BoxError> {
+        let mut text = String::new();
+        let mut usage = None;
+        for line in lines {
+            text.extend(parse_stream_line(kind, line, &mut usage)?);
+        }
+        let counts = usage.map(|usage: TokenUsage| (usage.input_tokens, usage.output_tokens));
+        Ok((text, counts))
+    }
+
+    #[test]
+    fn parses_openai_compatible_streams() {
+        let lines = [
+            r#"data: {"choices":[{"delta":{"role":"assistant","content":""}}]}"#,
+            r#"data: {"choices":[{"delta":{"content":"Hello"}}],"usage":null}"#,
+            r#"data:{"choices":[{"delta":{"content":" world"}}]}"#,
+            ": keep-alive",
+            "",
+            r#"data: {"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":2}}"#,
+            "data: [DONE]",
+        ];
+        let (text, usage) = parse(ApiKind::OpenAICompatible, &lines).unwrap();
+        assert_eq!(text, "Hello world");
+        assert_eq!(usage, Some((12, 2)));
+    }
+
+    #[test]
+    fn parses_anthropic_streams() {
+        let lines = [
+            "event: message_start",
+            r#"data: {"type":"message_start","message":{"usage":{"input_tokens":25,"output_tokens":1}}}"#,
+            r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"hm"}}"#,
+            r#"data: {"type":"ping"}"#,
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":15}}"#,
+            r#"data: {"type":"message_stop"}"#,
+        ];
+        let (text, usage) = parse(ApiKind::Anthropic, &lines).unwrap();
+        assert_eq!(text, "Hi");
+        assert_eq!(usage, Some((25, 15)));
+    }
+
+    #[test]
+    fn parses_google_streams() {
+        let lines = [
+            r#"data: {"candidates":[{"content":{"parts":[{"text":"One"},{"text":" two"}],"role":"model"}}],"usageMetadata":{"promptTokenCount":8,"candidatesTokenCount":2}}"#,
+            r#"data: {"candidates":[{"content":{"parts":[{"text":" three"}]}}],"usageMetadata":{"promptTokenCount":8,"candidatesTokenCount":3}}"#,
+        ];
+        let (text, usage) = parse(ApiKind::Google, &lines).unwrap();
+        assert_eq!(text, "One two three");
+        assert_eq!(usage, Some((8, 3)));
+    }
+
+    #[test]
+    fn parses_ollama_streams() {
+        let lines = [
+            r#"{"message":{"role":"assistant","content":"Local"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":" model"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":""},"done":true,"prompt_eval_count":30,"eval_count":4}"#,
+        ];
+        let (text, usage) = parse(ApiKind::Ollama, &lines).unwrap();
+        assert_eq!(text, "Local model");
+        assert_eq!(usage, Some((30, 4)));
+    }
+
+    #[test]
+    fn leaves_usage_unset_when_not_reported() {
+        let lines = [r#"data: {"choices":[{"delta":{"content":"x"}}]}"#];
+        assert_eq!(
+            parse(ApiKind::OpenAICompatible, &lines).unwrap(),
+            ("x".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn fails_on_error_events() {
+        let cases = [
+            (
+                ApiKind::Anthropic,
+                r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+                "Overloaded (overloaded_error)",
+            ),
+            (
+                ApiKind::OpenAICompatible,
+                r#"data: {"error":{"message":"Rate limit reached","type":"requests"}}"#,
+                "Rate limit reached (requests)",
+            ),
+            (
+                ApiKind::Google,
+                r#"data: {"error":{"code":503,"message":"The model is overloaded.","status":"UNAVAILABLE"}}"#,
+                "The model is overloaded.",
+            ),
+            (
+                ApiKind::Ollama,
+                r#"{"error":"model requires more system memory"}"#,
+                "model requires more system memory",
+            ),
+        ];
+        for (kind, line, message) in cases {
+            let lines = [line];
+            let err = parse(kind, &lines).unwrap_err().to_string();
+            assert!(err.contains(message), "{:?}: {}", kind, err);
+        }
+    }
+}
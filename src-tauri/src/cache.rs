@@ -0,0 +1,276 @@
+use crate::settings::{AppState, ProviderConfig};
+use chrono::{DateTime, Duration, Utc};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{Manager, State};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Default cap on the total size of cached responses
+pub const DEFAULT_MAX_CACHE_BYTES: u64 = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Config that produced the response
+    provider_name: String,
+    response: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+/// The config settings that shape a request, and so its response. Anything
+/// else, such as the config name, API keys, headers, proxy, retries and rate
+/// limits, is left out, so renaming a config or rotating its key keeps its
+/// cached responses.
+#[derive(Serialize)]
+struct CacheTarget<'a> {
+    provider: &'a str,
+    model: &'a str,
+    base_url: Option<&'a str>,
+    temperature: f32,
+    max_tokens: u32,
+    top_p: Option<f32>,
+    top_k: Option<u32>,
+    stop: &'a [String],
+    seed: Option<u64>,
+    reasoning_effort: Option<&'a str>,
+    thinking_budget: Option<u32>,
+    system_prompt: Option<&'a str>,
+    /// Endpoint, body and response pointer of a custom HTTP provider
+    custom_http: Option<[&'a str; 3]>,
+    /// Program and arguments of a command provider
+    command: Option<(&'a str, &'a [String])>,
+}
+
+impl<'a> CacheTarget<'a> {
+    fn of(config: &'a ProviderConfig) -> Self {
+        Self {
+            provider: &config.provider,
+            model: &config.model,
+            base_url: config.base_url.as_deref(),
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+            top_p: config.top_p,
+            top_k: config.top_k,
+            stop: &config.stop,
+            seed: config.seed,
+            reasoning_effort: config.reasoning_effort.as_deref(),
+            thinking_budget: config.thinking_budget,
+            system_prompt: config.system_prompt.as_deref(),
+            custom_http: config.custom_http.as_ref().map(|http| {
+                [
+                    http.url.as_str(),
+                    http.body_template.as_str(),
+                    http.response_pointer.as_str(),
+                ]
+            }),
+            command: config
+                .command
+                .as_ref()
+                .map(|command| (command.program.as_str(), command.args.as_slice())),
+        }
+    }
+}
+
+/// Cache key for a prompt sent to a provider: the request-shaping config
+/// settings, both system prompts and the final prompt text
+pub fn cache_key(config: &ProviderConfig, prompt_system: Option<&str>, prompt: &str) -> String {
+    let key = serde_json::json!({
+        "config": CacheTarget::of(config),
+        "prompt_system": prompt_system,
+        "prompt": prompt,
+    });
+
+    digest(&SHA256, key.to_string().as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Responses to earlier prompts, stored next to the settings file so re-running
+/// a prompt on the same text doesn't cost another request
+pub struct ResponseCache {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl ResponseCache {
+    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_dir = app_handle.path().app_config_dir()?;
+        fs::create_dir_all(&config_dir)?;
+        let path = config_dir.join("response_cache.json");
+
+        // A corrupt cache is only a missed saving, so start empty instead of failing
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// The cached response for a key, if it hasn't expired
+    pub fn get(&self, key: &str) -> Result<Option<String>, BoxError> {
+        let entries = self.entries.lock().map_err(|e| e.to_string())?;
+        Ok(entries
+            .get(key)
+            .filter(|entry| entry.expires_at > Utc::now())
+            .map(|entry| entry.response.clone()))
+    }
+
+    /// Store a response for `ttl_secs`, evicting the oldest entries once the
+    /// cached responses exceed `max_bytes`
+    pub fn insert(
+        &self,
+        key: String,
+        provider_name: &str,
+        response: &str,
+        ttl_secs: u64,
+        max_bytes: u64,
+    ) -> Result<(), BoxError> {
+        if response.len() as u64 > max_bytes {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let ttl = Duration::try_seconds(ttl_secs.try_into()?).ok_or("Cache TTL is too large")?;
+
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        entries.retain(|_, entry| entry.expires_at > now);
+        entries.insert(
+            key,
+            CacheEntry {
+                provider_name: provider_name.to_string(),
+                response: response.to_string(),
+                created_at: now,
+                expires_at: now + ttl,
+            },
+        );
+
+        let mut total: u64 = entries.values().map(|e| e.response.len() as u64).sum();
+        while total > max_bytes {
+            let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.created_at)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = entries.remove(&oldest) {
+                total -= entry.response.len() as u64;
+            }
+        }
+
+        self.save(&entries)
+    }
+
+    pub fn clear(&self) -> Result<(), BoxError> {
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        entries.clear();
+        self.save(&entries)
+    }
+
+    fn save(&self, entries: &HashMap<String, CacheEntry>) -> Result<(), BoxError> {
+        let contents = serde_json::to_string(entries)?;
+        fs::write(&self.path, contents)?;
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub async fn clear_response_cache(state: State<'_, AppState>) -> Result<(), String> {
+    state.response_cache.clear().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::custom_http::CustomHttpConfig;
+    use crate::local_command::CommandConfig;
+
+    fn key(config: &ProviderConfig) -> String {
+        cache_key(config, Some("Be brief"), "Hello")
+    }
+
+    #[test]
+    fn differs_by_custom_http_url() {
+        let config = |url: &str| ProviderConfig {
+            provider: "custom_http".to_string(),
+            custom_http: Some(CustomHttpConfig {
+                url: url.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_ne!(
+            key(&config("https://a.example.com/generate")),
+            key(&config("https://b.example.com/generate"))
+        );
+    }
+
+    #[test]
+    fn differs_by_command_program() {
+        let config = |program: &str| ProviderConfig {
+            provider: "command".to_string(),
+            command: Some(CommandConfig {
+                program: program.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_ne!(key(&config("llm")), key(&config("ollama")));
+    }
+
+    #[test]
+    fn differs_by_sampling_and_prompts() {
+        let config = ProviderConfig::default();
+        let warmer = ProviderConfig {
+            temperature: 0.9,
+            ..Default::default()
+        };
+        let reasoning = ProviderConfig {
+            reasoning_effort: Some("high".to_string()),
+            ..Default::default()
+        };
+        let with_system = ProviderConfig {
+            system_prompt: Some("Answer in French".to_string()),
+            ..Default::default()
+        };
+        assert_ne!(key(&config), key(&warmer));
+        assert_ne!(key(&config), key(&reasoning));
+        assert_ne!(key(&config), key(&with_system));
+        assert_ne!(key(&config), cache_key(&config, None, "Hello"));
+        assert_ne!(key(&config), cache_key(&config, Some("Be brief"), "Hi"));
+    }
+
+    #[test]
+    fn ignores_name_keys_and_request_handling() {
+        let config = ProviderConfig {
+            name: "openai".to_string(),
+            provider: "openai".to_string(),
+            api_key: "sk-one".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            ..Default::default()
+        };
+        let renamed = ProviderConfig {
+            name: "work".to_string(),
+            api_key: "secret:work".to_string(),
+            api_keys: vec!["sk-two".to_string()],
+            stream: true,
+            max_retries: Some(3),
+            requests_per_minute: Some(10),
+            headers: [("api-key".to_string(), "secret:work#header".to_string())].into(),
+            proxy_url: Some("http://proxy:8080".to_string()),
+            ..config.clone()
+        };
+        assert_eq!(key(&config), key(&renamed));
+    }
+}
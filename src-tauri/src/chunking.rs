@@ -0,0 +1,139 @@
+use crate::usage::estimate_tokens;
+use serde::{Deserialize, Serialize};
+
+/// How a prompt handles selections too long for a single request. The
+/// selection is split into chunks, the prompt template is run on each chunk,
+/// and the partial results are optionally combined by a reduce template.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkingConfig {
+    /// Token budget for the selected text in each chunk
+    pub chunk_tokens: u64,
+    /// Template run over the partial results, which replace `{{selectedText}}`.
+    /// Without it the partial results are joined with blank lines.
+    #[serde(default)]
+    pub reduce_prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkStage {
+    Map,
+    Reduce,
+}
+
+/// Progress of a prompt run over a chunked selection
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkProgress {
+    pub stage: ChunkStage,
+    /// Chunks finished so far in this stage
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Split text into chunks of at most `budget` estimated tokens, breaking on
+/// paragraph boundaries. Paragraphs longer than the budget are split between
+/// words. Windows line endings are normalised, so their blank lines count as
+/// paragraph breaks too.
+pub fn split_into_chunks(text: &str, budget: u64) -> Vec<String> {
+    let budget = budget.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    let text = text.replace("\r\n", "\n");
+    let paragraphs = text.split("\n\n").map(str::trim).filter(|p| !p.is_empty());
+    for paragraph in paragraphs {
+        let pieces = if estimate_tokens(paragraph) > budget {
+            split_paragraph(paragraph, budget)
+        } else {
+            vec![paragraph.to_string()]
+        };
+
+        // Pieces of a split paragraph that end up in one chunk stay one paragraph
+        for (index, piece) in pieces.iter().enumerate() {
+            if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(piece) > budget {
+                chunks.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push_str(if index == 0 { "\n\n" } else { " " });
+            }
+            current.push_str(piece);
+        }
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn split_paragraph(paragraph: &str, budget: u64) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+
+    for word in paragraph.split_whitespace() {
+        if !current.is_empty() && estimate_tokens(&current) + estimate_tokens(word) + 1 > budget {
+            pieces.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_short_text_in_one_chunk() {
+        assert_eq!(
+            split_into_chunks("First.\n\nSecond.", 100),
+            vec!["First.\n\nSecond."]
+        );
+    }
+
+    #[test]
+    fn breaks_between_paragraphs() {
+        let text = "aaaa aaaa\n\nbbbb bbbb\n\ncccc cccc";
+        assert_eq!(
+            split_into_chunks(text, 5),
+            vec!["aaaa aaaa", "bbbb bbbb", "cccc cccc"]
+        );
+    }
+
+    #[test]
+    fn breaks_on_windows_blank_lines() {
+        let text = "aaaa aaaa\r\n\r\nbbbb bbbb\r\nstill b";
+        assert_eq!(
+            split_into_chunks(text, 5),
+            vec!["aaaa aaaa", "bbbb bbbb\nstill b"]
+        );
+    }
+
+    #[test]
+    fn splits_long_paragraphs_between_words() {
+        let chunks = split_into_chunks("one two three four five six seven eight", 4);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| estimate_tokens(chunk) <= 4));
+        assert_eq!(chunks.join(" "), "one two three four five six seven eight");
+    }
+
+    #[test]
+    fn keeps_words_longer_than_the_budget_whole() {
+        let word = "x".repeat(40);
+        assert_eq!(split_into_chunks(&word, 2), vec![word.clone()]);
+    }
+
+    #[test]
+    fn skips_blank_paragraphs_and_empty_text() {
+        assert!(split_into_chunks("", 10).is_empty());
+        assert!(split_into_chunks("\n\n  \n\n", 10).is_empty());
+        assert_eq!(split_into_chunks("\n\n a \n\n\n\n b ", 0), vec!["a", "b"]);
+    }
+}
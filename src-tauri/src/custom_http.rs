@@ -0,0 +1,192 @@
+use crate::backends::{self, ChatResponse};
+use crate::retry::ProviderError;
+use crate::settings::ProviderConfig;
+use llm::chat::ChatMessage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Provider type of endpoints described entirely in settings
+pub const CUSTOM_HTTP_PROVIDER: &str = "custom_http";
+
+const PROMPT_PLACEHOLDER: &str = "{{prompt}}";
+const MODEL_PLACEHOLDER: &str = "{{model}}";
+const API_KEY_PLACEHOLDER: &str = "{{api_key}}";
+
+/// Settings of a `custom_http` provider, for inference APIs no backend supports
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomHttpConfig {
+    /// Endpoint the request is POSTed to
+    pub url: String,
+    /// Request headers; `{{api_key}}` in a value is replaced by the config's key,
+    /// e.g. `Authorization: Bearer {{api_key}}`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// JSON request body, with `{{prompt}}` and `{{model}}` in its strings,
+    /// e.g. `{"model": "{{model}}", "input": "{{prompt}}"}`
+    pub body_template: String,
+    /// JSON pointer to the response text, e.g. `/output/0/text`
+    pub response_pointer: String,
+}
+
+pub fn is_custom_http(provider: &str) -> bool {
+    provider.eq_ignore_ascii_case(CUSTOM_HTTP_PROVIDER)
+}
+
+fn settings(config: &ProviderConfig) -> Result<&CustomHttpConfig, BoxError> {
+    config
+        .custom_http
+        .as_ref()
+        .ok_or_else(|| format!("'{}' has no custom HTTP settings", config.name).into())
+}
+
+pub fn validate(config: &ProviderConfig) -> Result<(), BoxError> {
+    let http = settings(config)?;
+
+    let url =
+        reqwest::Url::parse(&http.url).map_err(|e| format!("Invalid URL '{}': {}", http.url, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("Invalid URL '{}': scheme must be http or https", http.url).into());
+    }
+
+    serde_json::from_str::<Value>(&http.body_template)
+        .map_err(|e| format!("The body template is not valid JSON: {}", e))?;
+    if !http.body_template.contains(PROMPT_PLACEHOLDER) {
+        return Err(format!("The body template must contain {}", PROMPT_PLACEHOLDER).into());
+    }
+    if !http.response_pointer.is_empty() && !http.response_pointer.starts_with('/') {
+        return Err(format!(
+            "Invalid response path '{}': a JSON pointer starts with '/'",
+            http.response_pointer
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Replace the placeholders in every string of the body template. Working on
+/// the parsed template keeps the prompt properly escaped.
+fn render_body(template: &Value, prompt: &str, model: &str) -> Value {
+    match template {
+        Value::String(s) => Value::String(
+            s.replace(MODEL_PLACEHOLDER, model)
+                .replace(PROMPT_PLACEHOLDER, prompt),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_body(item, prompt, model))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render_body(value, prompt, model)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Send a conversation to the endpoint. The config's API key must already be
+/// resolved.
+pub async fn chat(
+    client: &reqwest::Client,
+    config: &ProviderConfig,
+    messages: &[ChatMessage],
+) -> Result<ChatResponse, BoxError> {
+    let http = settings(config)?;
+    let template: Value = serde_json::from_str(&http.body_template)
+        .map_err(|e| format!("The body template is not valid JSON: {}", e))?;
+    let prompt = backends::flatten_conversation(messages);
+    let body = render_body(&template, &prompt, &config.model);
+
+    let mut request = client.post(&http.url).json(&body);
+    for (name, value) in &http.headers {
+        request = request.header(name, value.replace(API_KEY_PLACEHOLDER, &config.api_key));
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let headers = response.headers().clone();
+        let body = response.text().await.unwrap_or_default();
+        return Err(ProviderError::from_response(&config.name, status, &headers, &body).into());
+    }
+
+    let json: Value = response.json().await?;
+    let text = match json.pointer(&http.response_pointer) {
+        Some(Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => {
+            return Err(format!(
+                "No value at '{}' in the response from '{}'",
+                http.response_pointer, config.name
+            )
+            .into())
+        }
+    };
+
+    Ok(ChatResponse { text, usage: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fills_placeholders_in_nested_strings() {
+        let template = json!({
+            "model": "{{model}}",
+            "input": [{ "role": "user", "content": "Q: {{prompt}}" }],
+            "max_tokens": 100,
+            "stream": false,
+        });
+        assert_eq!(
+            render_body(&template, "Hi", "tiny"),
+            json!({
+                "model": "tiny",
+                "input": [{ "role": "user", "content": "Q: Hi" }],
+                "max_tokens": 100,
+                "stream": false,
+            })
+        );
+    }
+
+    #[test]
+    fn keeps_the_prompt_as_plain_text() {
+        let prompt = "He said \"hi\"\n\t{\"x\": 1} \\ done";
+        let rendered = render_body(&json!({ "text": "{{prompt}}" }), prompt, "m");
+        assert_eq!(rendered["text"], prompt);
+        let body = serde_json::to_string(&rendered).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap()["text"],
+            prompt
+        );
+    }
+
+    #[test]
+    fn leaves_placeholders_in_the_prompt_alone() {
+        let rendered = render_body(&json!("{{prompt}}"), "use {{model}} here", "tiny");
+        assert_eq!(rendered, json!("use {{model}} here"));
+    }
+
+    #[test]
+    fn replaces_every_occurrence_but_not_keys() {
+        let template = json!({ "{{prompt}}": "{{prompt}} and {{prompt}}" });
+        assert_eq!(
+            render_body(&template, "x", "m"),
+            json!({ "{{prompt}}": "x and x" })
+        );
+    }
+
+    #[test]
+    fn leaves_non_string_values_alone() {
+        let template = json!([null, true, 1.5, []]);
+        assert_eq!(render_body(&template, "x", "m"), template);
+    }
+}
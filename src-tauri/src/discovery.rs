@@ -0,0 +1,244 @@
+use crate::backends;
+use crate::key_pool;
+use crate::keys::{self, ENV_PREFIX};
+use crate::settings::{AppState, CommandType, ProviderConfig};
+use serde::Serialize;
+use std::time::Duration;
+use tauri::State;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Environment variables checked for API keys, with the provider each belongs
+/// to and the model a discovered config starts with. The first variable found
+/// for a provider wins.
+const KEY_VARIABLES: [(&str, &str, &str); 7] = [
+    ("OPENAI_API_KEY", "openai", "gpt-4o-mini"),
+    ("ANTHROPIC_API_KEY", "anthropic", "claude-3-5-haiku-latest"),
+    ("GEMINI_API_KEY", "google", "gemini-2.0-flash"),
+    ("GOOGLE_API_KEY", "google", "gemini-2.0-flash"),
+    ("GROQ_API_KEY", "groq", "llama-3.3-70b-versatile"),
+    ("DEEPSEEK_API_KEY", "deepseek", "deepseek-chat"),
+    ("XAI_API_KEY", "xai", "grok-2-latest"),
+];
+
+const OLLAMA_PROVIDER: &str = "ollama";
+
+/// Part of the names of Ollama models that only compute embeddings, such as
+/// `nomic-embed-text`, and can't answer prompts
+const EMBEDDING_MODEL_MARKER: &str = "embed";
+
+/// A running Ollama answers at once, so don't hold up discovery when there is none
+const OLLAMA_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Probes of hosted providers need longer, but a hung one mustn't stall
+/// discovery for the full request timeout
+const ENV_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A provider config added by discovery
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredProvider {
+    pub name: String,
+    pub provider: String,
+    pub model: String,
+    /// Where the provider was found, e.g. `env:OPENAI_API_KEY` or the Ollama URL
+    pub source: String,
+    /// Why the probe request with the config failed
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiscoveryReport {
+    /// Providers that answered a probe. Ones that already had a config are
+    /// left out.
+    pub added: Vec<DiscoveredProvider>,
+    /// Providers found but not added, as their probe failed
+    pub failed: Vec<DiscoveredProvider>,
+    /// The first working config, which prompts without a usable provider now use
+    pub default_provider: Option<String>,
+    pub updated_prompts: usize,
+}
+
+fn discovered_config(provider: &str, api_key: String, model: &str) -> ProviderConfig {
+    ProviderConfig {
+        name: provider.to_string(),
+        provider: provider.to_string(),
+        api_key,
+        model: model.to_string(),
+        temperature: 0.7,
+        max_tokens: 1024,
+        ..Default::default()
+    }
+}
+
+/// Configs for the keys set in well-known environment variables. Keys are
+/// referenced as `env:VAR`, so they never end up in settings.json.
+fn env_candidates() -> Vec<(ProviderConfig, String)> {
+    let mut candidates: Vec<(ProviderConfig, String)> = Vec::new();
+    for (variable, provider, model) in KEY_VARIABLES {
+        let found = candidates
+            .iter()
+            .any(|(config, _)| config.provider == provider);
+        if found || keys::read_env_key(variable).is_err() {
+            continue;
+        }
+        let reference = format!("{}{}", ENV_PREFIX, variable);
+        candidates.push((
+            discovered_config(provider, reference.clone(), model),
+            reference,
+        ));
+    }
+    candidates
+}
+
+/// Config for a local Ollama, using the first chat model it has pulled
+async fn ollama_candidate(client: &reqwest::Client) -> Option<(ProviderConfig, String)> {
+    let mut config = discovered_config(OLLAMA_PROVIDER, String::new(), "");
+    let models = tokio::time::timeout(OLLAMA_PROBE_TIMEOUT, backends::list_models(client, &config))
+        .await
+        .ok()?
+        .ok()?;
+    config.model = models
+        .into_iter()
+        .find(|model| !model.id.to_lowercase().contains(EMBEDDING_MODEL_MARKER))?
+        .id;
+
+    let source = backends::default_base_url(OLLAMA_PROVIDER)?.to_string();
+    Some((config, source))
+}
+
+/// Whether prompts using the config fail for lack of an API key, as with the
+/// "default" config created on first launch
+fn needs_key(config: &ProviderConfig) -> bool {
+    key_pool::pool_keys(config).is_empty()
+        && !backends::is_builtin(&config.provider)
+        && !config.provider.eq_ignore_ascii_case(OLLAMA_PROVIDER)
+}
+
+/// Whether an existing config already covers a discovered one: it has the
+/// same name, is a keyless config for the same provider, or holds the same
+/// key under any name, whether as the same reference, as plain text or in
+/// the vault. Keys in a locked vault can't be compared.
+fn is_known(state: &AppState, existing: &[ProviderConfig], config: &ProviderConfig) -> bool {
+    let key = state
+        .resolve_api_key(&config.api_key)
+        .ok()
+        .filter(|key| !key.is_empty());
+    existing.iter().any(|c| {
+        let same_key = || {
+            key_pool::pool_keys(c).into_iter().any(|existing_key| {
+                existing_key == config.api_key || state.resolve_api_key(existing_key).ok() == key
+            })
+        };
+        c.name == config.name
+            || (c.provider.eq_ignore_ascii_case(&config.provider) && c.api_key == config.api_key)
+            || (key.is_some() && same_key())
+    })
+}
+
+/// Add configs for the API keys in the environment and a local Ollama that
+/// answer a probe, and point prompts whose provider can't work at the first
+/// of them
+pub async fn discover(state: &AppState) -> Result<DiscoveryReport, BoxError> {
+    let mut candidates = env_candidates();
+    candidates.extend(ollama_candidate(&state.http_client).await);
+
+    let existing = state
+        .settings_manager
+        .get_all_llm_configs()
+        .map_err(|e| e.to_string())?;
+
+    let mut report = DiscoveryReport::default();
+    for (config, source) in candidates {
+        // Leave the user's own configs alone
+        if is_known(state, &existing, &config) {
+            continue;
+        }
+
+        let error =
+            match tokio::time::timeout(ENV_PROBE_TIMEOUT, state.test_llm_config(&config)).await {
+                Ok(result) if result.success => None,
+                Ok(result) => Some(result.error.unwrap_or_default()),
+                Err(_) => Some(format!(
+                    "No answer within {} seconds",
+                    ENV_PROBE_TIMEOUT.as_secs()
+                )),
+            };
+        println!(
+            "Discovered '{}' from {} ({})",
+            config.name,
+            source,
+            if error.is_none() {
+                "working"
+            } else {
+                "not working"
+            }
+        );
+        let mut discovered = DiscoveredProvider {
+            name: config.name.clone(),
+            provider: config.provider.clone(),
+            model: config.model.clone(),
+            source,
+            error,
+        };
+        // A config that fails now would only break the prompts pointed at it
+        if discovered.error.is_some() {
+            report.failed.push(discovered);
+            continue;
+        }
+
+        // One config that can't be saved shouldn't lose the others
+        if let Err(err) = state.settings_manager.add_llm_config(config.clone()) {
+            discovered.error = Some(err.to_string());
+            report.failed.push(discovered);
+            continue;
+        }
+        state.forget_llm_instances(&config.name).await;
+
+        if report.default_provider.is_none() {
+            report.default_provider = Some(config.name);
+        }
+        report.added.push(discovered);
+    }
+
+    if let Some(provider_name) = &report.default_provider {
+        report.updated_prompts = repoint_prompts(state, provider_name)?;
+    }
+    Ok(report)
+}
+
+/// Point the prompts whose provider config is missing or has no key at
+/// `provider_name`, returning how many were changed
+fn repoint_prompts(state: &AppState, provider_name: &str) -> Result<usize, BoxError> {
+    let settings = &state.settings_manager;
+    let configs = settings.get_all_llm_configs().map_err(|e| e.to_string())?;
+    let groups = settings.get_provider_groups().map_err(|e| e.to_string())?;
+    let mut shortcuts = settings.get_shortcuts().map_err(|e| e.to_string())?;
+
+    let mut updated = 0;
+    for shortcut in &mut shortcuts {
+        let CommandType::Prompt(command) = &mut shortcut.command else {
+            continue;
+        };
+        let is_group = groups.iter().any(|g| g.name == command.provider_name);
+        let usable = configs
+            .iter()
+            .find(|c| c.name == command.provider_name)
+            .is_some_and(|c| !needs_key(c));
+        if !is_group && !usable {
+            command.provider_name = provider_name.to_string();
+            updated += 1;
+        }
+    }
+
+    if updated > 0 {
+        settings
+            .update_shortcuts(shortcuts)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn discover_providers(state: State<'_, AppState>) -> Result<DiscoveryReport, String> {
+    discover(&state).await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,229 @@
+use crate::settings::ProviderConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a rejected or rate-limited key is skipped when the config sets no cooldown
+const DEFAULT_KEY_COOLDOWN_SECS: u64 = 60;
+
+/// How a config with several API keys picks the key for a request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRotation {
+    /// Use the keys in turn
+    #[default]
+    RoundRobin,
+    /// Prefer keys that were never rate limited, then the one limited longest ago
+    LeastRecentlyLimited,
+}
+
+/// Rotation state of one config's keys
+#[derive(Debug, Default)]
+struct PoolState {
+    /// Position in the pool to start looking for the next key
+    next: usize,
+    /// When each key was last rejected or rate limited, by key reference
+    limited_at: HashMap<String, Instant>,
+}
+
+/// Rotation state of all key pools, keyed by config name. It is kept in
+/// memory only, so cooldowns end when the app restarts.
+#[derive(Debug, Default)]
+pub struct KeyPools {
+    pools: Mutex<HashMap<String, PoolState>>,
+}
+
+/// All keys of a config: its API key followed by the keys of its pool
+pub fn pool_keys(config: &ProviderConfig) -> Vec<&str> {
+    let mut keys: Vec<&str> = Vec::new();
+    for key in std::iter::once(&config.api_key).chain(&config.api_keys) {
+        if !key.is_empty() && !keys.contains(&key.as_str()) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+/// Whether requests with the config rotate between several keys
+pub fn is_pooled(config: &ProviderConfig) -> bool {
+    pool_keys(config).len() > 1
+}
+
+fn cooldown(config: &ProviderConfig) -> Duration {
+    Duration::from_secs(
+        config
+            .key_cooldown_secs
+            .unwrap_or(DEFAULT_KEY_COOLDOWN_SECS),
+    )
+}
+
+/// Positions of the keys not cooling down, starting at the pool's next key
+fn available(pool: &PoolState, keys: &[&str], cooldown: Duration) -> Vec<usize> {
+    let now = Instant::now();
+    (0..keys.len())
+        .map(|offset| (pool.next + offset) % keys.len())
+        .filter(|&index| {
+            pool.limited_at
+                .get(keys[index])
+                .is_none_or(|at| now.duration_since(*at) >= cooldown)
+        })
+        .collect()
+}
+
+impl KeyPools {
+    /// Pick the key reference for the next request with a config, or `None`
+    /// if every key is cooling down
+    pub fn select(&self, config: &ProviderConfig) -> Option<String> {
+        let keys = pool_keys(config);
+        if keys.is_empty() {
+            return Some(config.api_key.clone());
+        }
+
+        let mut pools = self.pools.lock().ok()?;
+        let pool = pools.entry(config.name.clone()).or_default();
+        let candidates = available(pool, &keys, cooldown(config));
+
+        // Ties keep rotation order, so unlimited keys are still used in turn
+        let index = match config.key_rotation {
+            KeyRotation::RoundRobin => candidates.first().copied(),
+            KeyRotation::LeastRecentlyLimited => candidates
+                .iter()
+                .copied()
+                .min_by_key(|&index| pool.limited_at.get(keys[index]).copied()),
+        }?;
+
+        pool.next = index + 1;
+        Some(keys[index].to_string())
+    }
+
+    /// Whether any key of the config is not cooling down
+    pub fn has_available(&self, config: &ProviderConfig) -> bool {
+        let keys = pool_keys(config);
+        let Ok(mut pools) = self.pools.lock() else {
+            return false;
+        };
+        let pool = pools.entry(config.name.clone()).or_default();
+        !available(pool, &keys, cooldown(config)).is_empty()
+    }
+
+    /// Skip a key for the config's cooldown after it was rejected or rate limited
+    pub fn cool_down(&self, config: &ProviderConfig, key: &str) {
+        if let Ok(mut pools) = self.pools.lock() {
+            pools
+                .entry(config.name.clone())
+                .or_default()
+                .limited_at
+                .insert(key.to_string(), Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pooled(rotation: KeyRotation) -> ProviderConfig {
+        ProviderConfig {
+            name: "groq".to_string(),
+            api_key: "key-1".to_string(),
+            api_keys: vec!["key-2".to_string(), "key-3".to_string()],
+            key_rotation: rotation,
+            ..Default::default()
+        }
+    }
+
+    fn next(pools: &KeyPools, config: &ProviderConfig) -> String {
+        pools.select(config).unwrap()
+    }
+
+    #[test]
+    fn lists_the_key_and_pool_once_each() {
+        let config = ProviderConfig {
+            api_key: "key-1".to_string(),
+            api_keys: vec!["key-2".to_string(), String::new(), "key-1".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(pool_keys(&config), ["key-1", "key-2"]);
+        assert!(is_pooled(&config));
+        assert!(!is_pooled(&ProviderConfig::default()));
+    }
+
+    #[test]
+    fn rotates_round_robin() {
+        let pools = KeyPools::default();
+        let config = pooled(KeyRotation::RoundRobin);
+        let keys: Vec<String> = (0..4).map(|_| next(&pools, &config)).collect();
+        assert_eq!(keys, ["key-1", "key-2", "key-3", "key-1"]);
+    }
+
+    #[test]
+    fn skips_keys_cooling_down() {
+        let pools = KeyPools::default();
+        let config = pooled(KeyRotation::RoundRobin);
+        pools.cool_down(&config, "key-1");
+        pools.cool_down(&config, "key-2");
+        assert_eq!(next(&pools, &config), "key-3");
+        assert_eq!(next(&pools, &config), "key-3");
+
+        pools.cool_down(&config, "key-3");
+        assert!(!pools.has_available(&config));
+        assert_eq!(pools.select(&config), None);
+    }
+
+    #[test]
+    fn uses_keys_again_after_the_cooldown() {
+        let pools = KeyPools::default();
+        let config = ProviderConfig {
+            key_cooldown_secs: Some(0),
+            ..pooled(KeyRotation::RoundRobin)
+        };
+        for key in pool_keys(&config) {
+            pools.cool_down(&config, key);
+        }
+        assert!(pools.has_available(&config));
+        assert_eq!(next(&pools, &config), "key-1");
+    }
+
+    #[test]
+    fn prefers_the_key_limited_longest_ago() {
+        let pools = KeyPools::default();
+        let config = ProviderConfig {
+            key_cooldown_secs: Some(0),
+            ..pooled(KeyRotation::LeastRecentlyLimited)
+        };
+        pools.cool_down(&config, "key-2");
+        pools.cool_down(&config, "key-1");
+        assert_eq!(next(&pools, &config), "key-3");
+
+        pools.cool_down(&config, "key-3");
+        assert_eq!(next(&pools, &config), "key-2");
+    }
+
+    #[test]
+    fn keeps_each_config_in_its_own_rotation() {
+        let pools = KeyPools::default();
+        let groq = pooled(KeyRotation::RoundRobin);
+        let gemini = ProviderConfig {
+            name: "gemini".to_string(),
+            ..groq.clone()
+        };
+        assert_eq!(next(&pools, &groq), "key-1");
+        assert_eq!(next(&pools, &groq), "key-2");
+        assert_eq!(next(&pools, &gemini), "key-1");
+    }
+
+    #[test]
+    fn uses_the_only_key_without_rotating() {
+        let pools = KeyPools::default();
+        let config = ProviderConfig {
+            api_key: "env:OPENAI_API_KEY".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(next(&pools, &config), "env:OPENAI_API_KEY");
+        assert_eq!(
+            pools.select(&ProviderConfig::default()),
+            Some(String::new())
+        );
+    }
+}
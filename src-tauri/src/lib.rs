@@ -1,13 +1,27 @@
+mod backends;
+mod cache;
+mod chunking;
 mod commands;
+mod custom_http;
+mod discovery;
+mod key_pool;
+mod keys;
+mod local_command;
+mod mock;
+mod preflight;
 mod query;
+mod rate_limit;
+mod retry;
 mod settings;
 mod shortcut;
+mod usage;
+mod vault;
 
 use settings::AppState;
 use tauri::{
     menu::{Menu, MenuEvent, MenuItem},
     tray::TrayIconBuilder,
-    AppHandle, Manager, WebviewUrl, WebviewWindowBuilder,
+    AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder,
 };
 
 #[tauri::command]
@@ -118,8 +132,41 @@ pub fn run() {
 
             let app_state =
                 AppState::new(&app.app_handle()).expect("Failed to initialize LLM config state");
+            let first_run = app_state.settings_manager.is_first_run();
+            // Keys can only be moved into the vault once it is unlocked, which
+            // the settings window asks for
+            match app_state.plaintext_key_count() {
+                Ok(0) => {}
+                Ok(count) => println!(
+                    "{} API keys are saved in plain text, unlock or create the secrets vault in Settings to encrypt them",
+                    count
+                ),
+                Err(err) => println!("Error checking for plaintext API keys: {}", err),
+            }
             app.manage(app_state);
 
+            // Replace the keyless default provider with whatever this machine has set up
+            if first_run {
+                let app_handle = app.app_handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let state = app_handle.state::<AppState>();
+                    match discovery::discover(&state).await {
+                        Ok(report) => {
+                            println!(
+                                "Discovered {} providers, default is {:?}",
+                                report.added.len(),
+                                report.default_provider
+                            );
+                            // An open settings window reloads, so it doesn't save over the new configs
+                            if let Err(err) = app_handle.emit("providers-discovered", &report) {
+                                println!("Failed to emit providers-discovered event: {}", err);
+                            }
+                        }
+                        Err(err) => println!("Error discovering providers: {}", err),
+                    }
+                });
+            }
+
             // Initialize ShortcutManager
             shortcut::enable_shortcuts(app);
             setup_system_tray(&app.app_handle())?;
@@ -129,10 +176,27 @@ pub fn run() {
             greet,
             query::submit_prompt,
             query::register_llm,
+            query::delete_llm_config,
             query::get_llm_configs,
+            query::list_models,
+            query::test_llm_config,
+            query::get_provider_groups,
+            query::register_provider_group,
             query::register_custom_prompt,
             query::get_custom_prompts,
             query::execute_custom_prompt,
+            query::cancel_prompt,
+            query::follow_up,
+            usage::get_usage_report,
+            usage::get_model_prices,
+            usage::update_model_prices,
+            cache::clear_response_cache,
+            preflight::get_context_windows,
+            preflight::update_context_windows,
+            discovery::discover_providers,
+            vault::get_vault_status,
+            vault::unlock_vault,
+            vault::lock_vault,
             shortcut::get_shortcuts,
             shortcut::unregister_shortcut,
             shortcut::update_shortcut,
@@ -0,0 +1,226 @@
+use crate::backends::{self, ChatResponse};
+use crate::keys::expand_home;
+use crate::retry::ProviderError;
+use crate::settings::ProviderConfig;
+use llm::chat::ChatMessage;
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Provider type of local executables such as `llama-cli` or `llm`
+pub const COMMAND_PROVIDER: &str = "command";
+
+const PROMPT_PLACEHOLDER: &str = "{{prompt}}";
+const MODEL_PLACEHOLDER: &str = "{{model}}";
+
+/// Upper bound for a run when the config sets no timeout, so a hung
+/// program can't block a prompt forever
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// Variables always passed to the program, so it can be found and run
+const BASE_ENV: [&str; 3] = ["PATH", "HOME", "USERPROFILE"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptInput {
+    /// The prompt is written to the program's stdin
+    #[default]
+    Stdin,
+    /// The prompt replaces `{{prompt}}` in the arguments, or is appended as
+    /// the last argument
+    Argument,
+}
+
+/// Settings of a `command` provider. The program's stdout is the response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommandConfig {
+    pub program: String,
+    /// Arguments, where `{{model}}` is replaced by the config's model
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub prompt_input: PromptInput,
+    /// Environment variables passed on to the program. Unset, the program
+    /// inherits the whole environment.
+    #[serde(default)]
+    pub env_passthrough: Option<Vec<String>>,
+}
+
+pub fn is_command(provider: &str) -> bool {
+    provider.eq_ignore_ascii_case(COMMAND_PROVIDER)
+}
+
+fn settings(config: &ProviderConfig) -> Result<&CommandConfig, BoxError> {
+    config
+        .command
+        .as_ref()
+        .ok_or_else(|| format!("'{}' has no command settings", config.name).into())
+}
+
+pub fn validate(config: &ProviderConfig) -> Result<(), BoxError> {
+    let command = settings(config)?;
+    if command.program.trim().is_empty() {
+        return Err("The program to run is required".into());
+    }
+    Ok(())
+}
+
+/// Run the program on a conversation, flattened into one prompt
+pub async fn chat(
+    config: &ProviderConfig,
+    messages: &[ChatMessage],
+) -> Result<ChatResponse, BoxError> {
+    let command = settings(config)?;
+    let prompt = backends::flatten_conversation(messages);
+
+    let mut args: Vec<String> = command
+        .args
+        .iter()
+        .map(|arg| arg.replace(MODEL_PLACEHOLDER, &config.model))
+        .collect();
+    if command.prompt_input == PromptInput::Argument {
+        if args.iter().any(|arg| arg.contains(PROMPT_PLACEHOLDER)) {
+            for arg in &mut args {
+                *arg = arg.replace(PROMPT_PLACEHOLDER, &prompt);
+            }
+        } else {
+            args.push(prompt.clone());
+        }
+    }
+
+    let mut process = Command::new(expand_home(&command.program));
+    process
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(passthrough) = &command.env_passthrough {
+        process.env_clear();
+        for name in BASE_ENV
+            .iter()
+            .copied()
+            .chain(passthrough.iter().map(String::as_str))
+        {
+            if let Some(value) = std::env::var_os(name) {
+                process.env(name, value);
+            }
+        }
+    }
+    process.stdin(match command.prompt_input {
+        PromptInput::Stdin => Stdio::piped(),
+        PromptInput::Argument => Stdio::null(),
+    });
+
+    let mut child = process
+        .spawn()
+        .map_err(|e| format!("Failed to start '{}': {}", command.program, e))?;
+
+    // Write the prompt while the output is read, so a program that answers
+    // before reading all of its input can't deadlock on a full pipe
+    if let Some(mut input) = child.stdin.take() {
+        let bytes = prompt.into_bytes();
+        tokio::spawn(async move {
+            if let Err(err) = input.write_all(&bytes).await {
+                println!("Error writing prompt to command: {}", err);
+            }
+        });
+    }
+
+    // Dropping the child on timeout kills the program
+    let secs = config.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS);
+    let output = tokio::time::timeout(Duration::from_secs(secs), child.wait_with_output())
+        .await
+        .map_err(|_| ProviderError::timeout(secs))??;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "'{}' failed ({}): {}",
+            command.program,
+            output.status,
+            stderr.trim()
+        )
+        .into());
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(ChatResponse { text, usage: None })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use llm::chat::ChatRole;
+
+    /// A config running `sh -c script`
+    fn shell(script: &str) -> ProviderConfig {
+        ProviderConfig {
+            name: "shell".to_string(),
+            provider: COMMAND_PROVIDER.to_string(),
+            command: Some(CommandConfig {
+                program: "sh".to_string(),
+                args: vec!["-c".to_string(), script.to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn run(config: &ProviderConfig, prompt: &str) -> Result<String, BoxError> {
+        let messages = [ChatMessage {
+            role: ChatRole::User,
+            content: prompt.to_string(),
+        }];
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime
+            .block_on(chat(config, &messages))
+            .map(|response| response.text)
+    }
+
+    #[test]
+    fn writes_the_prompt_to_stdin() {
+        assert_eq!(run(&shell("tr a-z A-Z"), "hello").unwrap(), "HELLO");
+    }
+
+    #[test]
+    fn reports_stderr_when_the_program_fails() {
+        let err = run(&shell("echo 'model not found' >&2; exit 3"), "hello").unwrap_err();
+        let err = err.to_string();
+        assert!(err.contains("model not found"), "{}", err);
+        assert!(err.contains("exit status: 3"), "{}", err);
+    }
+
+    #[test]
+    fn passes_only_the_listed_variables() {
+        std::env::set_var("QUILLBERT_TEST_KEPT", "kept");
+        std::env::set_var("QUILLBERT_TEST_DROPPED", "dropped");
+        let mut config = shell("echo \"$QUILLBERT_TEST_KEPT-$QUILLBERT_TEST_DROPPED\"");
+        assert_eq!(run(&config, "").unwrap(), "kept-dropped");
+
+        if let Some(command) = config.command.as_mut() {
+            command.env_passthrough = Some(vec!["QUILLBERT_TEST_KEPT".to_string()]);
+        }
+        assert_eq!(run(&config, "").unwrap(), "kept-");
+    }
+
+    #[test]
+    fn kills_the_program_on_timeout() {
+        let marker =
+            std::env::temp_dir().join(format!("quillbert-command-timeout-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let mut config = shell(&format!("sleep 2; touch '{}'", marker.display()));
+        config.timeout_secs = Some(1);
+
+        let err = run(&config, "hello").unwrap_err();
+        let err = err.downcast_ref::<ProviderError>().unwrap();
+        assert_eq!(err.status, Some(408));
+
+        // Had the program survived, it would have created the marker by now
+        std::thread::sleep(Duration::from_secs(2));
+        assert!(!marker.exists());
+    }
+}
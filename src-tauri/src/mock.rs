@@ -0,0 +1,230 @@
+use crate::backends::{ChatResponse, ModelInfo};
+use crate::keys::expand_home;
+use crate::retry::ProviderError;
+use crate::settings::ProviderConfig;
+use llm::chat::{ChatMessage, ChatRole};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Provider type of the built-in mock backend, for tests and offline demos
+pub const MOCK_PROVIDER: &str = "mock";
+
+/// The mock's models select how it answers
+const ECHO: &str = "echo";
+const UPPERCASE: &str = "uppercase";
+const FIXTURES: &str = "fixtures";
+
+/// Fixture used for prompts without one of their own
+const DEFAULT_FIXTURE: &str = "*";
+
+/// Settings of a `mock` provider. Its model picks the response: `echo`
+/// returns the prompt, `uppercase` returns it in upper case and `fixtures`
+/// returns the canned response for the prompt's name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockConfig {
+    /// JSON object mapping prompt names to responses, with `*` as the default
+    #[serde(default)]
+    pub fixtures_path: Option<String>,
+    /// Artificial delay before every response
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// Fail every n-th request to this config, to exercise retries and fallbacks
+    #[serde(default)]
+    pub fail_every: Option<u64>,
+    /// Status of the injected failures, 500 by default
+    #[serde(default)]
+    pub error_status: Option<u16>,
+}
+
+/// Requests served per config name, to decide which ones fail
+static REQUEST_COUNTS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+/// Start counting a config's requests afresh, e.g. once it has been saved, so
+/// its probe request doesn't shift which later ones fail
+pub fn reset_request_count(name: &str) {
+    if let Ok(mut counts) = REQUEST_COUNTS.lock() {
+        counts.remove(name);
+    }
+}
+
+pub fn is_mock(provider: &str) -> bool {
+    provider.eq_ignore_ascii_case(MOCK_PROVIDER)
+}
+
+pub fn list_models() -> Vec<ModelInfo> {
+    [ECHO, UPPERCASE, FIXTURES]
+        .into_iter()
+        .map(ModelInfo::new)
+        .collect()
+}
+
+pub fn validate(config: &ProviderConfig) -> Result<(), BoxError> {
+    match config.model.as_str() {
+        ECHO | UPPERCASE => Ok(()),
+        FIXTURES => {
+            let has_fixtures = config
+                .mock
+                .as_ref()
+                .is_some_and(|mock| mock.fixtures_path.is_some());
+            if has_fixtures {
+                Ok(())
+            } else {
+                Err("The mock 'fixtures' model needs a fixtures file".into())
+            }
+        }
+        model => Err(format!(
+            "Unknown mock model '{}', expected {}, {} or {}",
+            model, ECHO, UPPERCASE, FIXTURES
+        )
+        .into()),
+    }
+}
+
+/// Answer a conversation without any network access. The response only
+/// depends on the config, the prompt name and the last user message.
+pub async fn chat(
+    config: &ProviderConfig,
+    prompt_name: &str,
+    messages: &[ChatMessage],
+) -> Result<ChatResponse, BoxError> {
+    let mock = config.mock.clone().unwrap_or_default();
+    if let Some(latency_ms) = mock.latency_ms {
+        tokio::time::sleep(Duration::from_millis(latency_ms)).await;
+    }
+
+    let count = {
+        let mut counts = REQUEST_COUNTS.lock().map_err(|e| e.to_string())?;
+        let count = counts.entry(config.name.clone()).or_default();
+        *count += 1;
+        *count
+    };
+    if mock
+        .fail_every
+        .is_some_and(|every| every > 0 && count % every == 0)
+    {
+        return Err(ProviderError {
+            message: format!("mock returned an injected error for request {}", count),
+            status: Some(mock.error_status.unwrap_or(500)),
+            retry_after: None,
+        }
+        .into());
+    }
+
+    let prompt = messages
+        .iter()
+        .rev()
+        .find(|m| matches!(m.role, ChatRole::User))
+        .map(|m| m.content.as_str())
+        .unwrap_or_default();
+
+    let text = match config.model.as_str() {
+        ECHO => prompt.to_string(),
+        UPPERCASE => prompt.to_uppercase(),
+        FIXTURES => fixture(&mock, prompt_name)?,
+        model => return Err(format!("Unknown mock model '{}'", model).into()),
+    };
+
+    Ok(ChatResponse { text, usage: None })
+}
+
+/// Read the fixture for a prompt. The file is read on every request, so
+/// fixtures can be edited while the app runs.
+fn fixture(mock: &MockConfig, prompt_name: &str) -> Result<String, BoxError> {
+    let path = mock
+        .fixtures_path
+        .as_deref()
+        .ok_or("No fixtures file configured for the mock provider")?;
+    let contents = fs::read_to_string(expand_home(path))
+        .map_err(|e| format!("Failed to read fixtures file '{}': {}", path, e))?;
+    let mut fixtures: HashMap<String, String> = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid fixtures file '{}': {}", path, e))?;
+
+    fixtures
+        .remove(prompt_name)
+        .or_else(|| fixtures.remove(DEFAULT_FIXTURE))
+        .ok_or_else(|| format!("No fixture for prompt '{}' in '{}'", prompt_name, path).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(name: &str, model: &str, mock: MockConfig) -> ProviderConfig {
+        ProviderConfig {
+            name: name.to_string(),
+            provider: MOCK_PROVIDER.to_string(),
+            model: model.to_string(),
+            mock: Some(mock),
+            ..Default::default()
+        }
+    }
+
+    fn ask(config: &ProviderConfig, prompt_name: &str) -> Result<String, BoxError> {
+        let messages = [ChatMessage {
+            role: ChatRole::User,
+            content: "hello".to_string(),
+        }];
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime
+            .block_on(chat(config, prompt_name, &messages))
+            .map(|response| response.text)
+    }
+
+    #[test]
+    fn fails_every_nth_request_per_config() {
+        let flaky = config(
+            "mock-flaky",
+            ECHO,
+            MockConfig {
+                fail_every: Some(2),
+                error_status: Some(429),
+                ..Default::default()
+            },
+        );
+        let steady = config("mock-steady", UPPERCASE, MockConfig::default());
+
+        assert_eq!(ask(&flaky, "Fix Grammar").unwrap(), "hello");
+        assert_eq!(ask(&steady, "Fix Grammar").unwrap(), "HELLO");
+        let err = ask(&flaky, "Fix Grammar").unwrap_err();
+        let err = err.downcast_ref::<ProviderError>().unwrap();
+        assert_eq!(err.status, Some(429));
+        assert!(ask(&flaky, "Fix Grammar").is_ok());
+
+        // Saving the config starts the count again
+        reset_request_count("mock-flaky");
+        assert!(ask(&flaky, "Fix Grammar").is_ok());
+        assert!(ask(&flaky, "Fix Grammar").is_err());
+    }
+
+    #[test]
+    fn picks_the_fixture_for_the_prompt() {
+        let path = std::env::temp_dir().join(format!(
+            "quillbert-mock-fixtures-{}.json",
+            std::process::id()
+        ));
+        fs::write(&path, r#"{"Fix Grammar": "Fixed.", "*": "Default."}"#).unwrap();
+        let fixtures = config(
+            "mock-fixtures",
+            FIXTURES,
+            MockConfig {
+                fixtures_path: Some(path.to_string_lossy().into_owned()),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(ask(&fixtures, "Fix Grammar").unwrap(), "Fixed.");
+        assert_eq!(ask(&fixtures, "Summarize").unwrap(), "Default.");
+
+        fs::write(&path, r#"{"Fix Grammar": "Fixed."}"#).unwrap();
+        let err = ask(&fixtures, "Summarize").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("No fixture for prompt 'Summarize'"));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::settings::{AppState, ContextWindow};
+use crate::usage::estimate_tokens;
+use serde::Serialize;
+use std::sync::OnceLock;
+use tauri::State;
+use tiktoken_rs::CoreBPE;
+
+static TOKENIZER: OnceLock<Option<CoreBPE>> = OnceLock::new();
+
+/// Count the tokens of a text with the o200k tokenizer. Other vendors' models
+/// tokenize differently, so for them this is a close estimate rather than
+/// an exact count.
+pub fn count_tokens(text: &str) -> u64 {
+    let tokenizer = TOKENIZER.get_or_init(|| match tiktoken_rs::o200k_base() {
+        Ok(tokenizer) => Some(tokenizer),
+        Err(err) => {
+            println!(
+                "Failed to load tokenizer, estimating tokens instead: {}",
+                err
+            );
+            None
+        }
+    });
+
+    match tokenizer {
+        Some(tokenizer) => tokenizer.encode_with_special_tokens(text).len() as u64,
+        None => estimate_tokens(text),
+    }
+}
+
+/// Context window of a model. Entries match model names by prefix, so
+/// `claude-3-5-sonnet` also covers `claude-3-5-sonnet-20241022`; the longest
+/// matching entry wins.
+pub fn context_window(windows: &[ContextWindow], provider: &str, model: &str) -> Option<u64> {
+    windows
+        .iter()
+        .filter(|w| w.provider.eq_ignore_ascii_case(provider) && model.starts_with(&w.model))
+        .max_by_key(|w| w.model.len())
+        .map(|w| w.tokens)
+}
+
+/// A request that would not fit the context window of its model
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightCheck {
+    pub provider_name: String,
+    pub model: String,
+    /// Tokens of the rendered prompt and system prompts
+    pub prompt_tokens: u64,
+    /// Tokens reserved for the response
+    pub max_tokens: u64,
+    pub context_window: u64,
+}
+
+impl PreflightCheck {
+    pub fn fits(&self) -> bool {
+        self.prompt_tokens + self.max_tokens <= self.context_window
+    }
+}
+
+impl std::fmt::Display for PreflightCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The prompt is about {} tokens and {} more are reserved for the response, \
+             but {} ({}) has a context window of {} tokens",
+            self.prompt_tokens,
+            self.max_tokens,
+            self.provider_name,
+            self.model,
+            self.context_window
+        )
+    }
+}
+
+#[tauri::command]
+pub async fn get_context_windows(state: State<'_, AppState>) -> Result<Vec<ContextWindow>, String> {
+    state
+        .settings_manager
+        .get_context_windows()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_context_windows(
+    state: State<'_, AppState>,
+    windows: Vec<ContextWindow>,
+) -> Result<(), String> {
+    state
+        .settings_manager
+        .update_context_windows(windows)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(provider: &str, model: &str, tokens: u64) -> ContextWindow {
+        ContextWindow {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            tokens,
+        }
+    }
+
+    #[test]
+    fn longest_matching_prefix_wins() {
+        let windows = [
+            window("openai", "gpt-4", 8_192),
+            window("openai", "gpt-4o", 128_000),
+            window("openai", "gpt-4o-mini", 64_000),
+        ];
+        assert_eq!(
+            context_window(&windows, "openai", "gpt-4-0613"),
+            Some(8_192)
+        );
+        assert_eq!(
+            context_window(&windows, "openai", "gpt-4o-2024-08-06"),
+            Some(128_000)
+        );
+        assert_eq!(
+            context_window(&windows, "openai", "gpt-4o-mini"),
+            Some(64_000)
+        );
+    }
+
+    #[test]
+    fn matches_provider_case_insensitively_but_not_other_providers() {
+        let windows = [window("anthropic", "claude-3-5-sonnet", 200_000)];
+        assert_eq!(
+            context_window(&windows, "Anthropic", "claude-3-5-sonnet-20241022"),
+            Some(200_000)
+        );
+        assert_eq!(
+            context_window(&windows, "openrouter", "claude-3-5-sonnet"),
+            None
+        );
+    }
+
+    #[test]
+    fn unknown_models_have_no_window() {
+        let windows = [window("openai", "gpt-4o", 128_000)];
+        assert_eq!(context_window(&windows, "openai", "o1"), None);
+        assert_eq!(context_window(&windows, "openai", "gpt-4"), None);
+        assert_eq!(context_window(&[], "openai", "gpt-4o"), None);
+    }
+
+    #[test]
+    fn fits_counts_the_reserved_response_tokens() {
+        let check = |prompt_tokens| PreflightCheck {
+            provider_name: "openai".to_string(),
+            model: "gpt-4".to_string(),
+            prompt_tokens,
+            max_tokens: 1_000,
+            context_window: 8_000,
+        };
+        assert!(check(7_000).fits());
+        assert!(!check(7_001).fits());
+    }
+
+    #[test]
+    fn counts_tokens() {
+        assert_eq!(count_tokens(""), 0);
+        assert!(count_tokens("Hello, world!") > 0);
+    }
+}
@@ -1,29 +1,83 @@
-use crate::settings::{AppState, CommandType, ProviderConfig, ShortcutConfig};
+use crate::backends::ModelInfo;
+use crate::chunking::{ChunkProgress, ChunkingConfig};
+use crate::preflight::PreflightCheck;
+use crate::rate_limit::QueuedRequest;
+use crate::retry::RetryAttempt;
+use crate::settings::{
+    AppState, CommandType, ConfigTestResult, LlmConfigInfo, PromptAnswer, PromptCommand,
+    PromptListener, ProviderConfig, ProviderGroup, ShortcutConfig,
+};
+use futures_util::future::{AbortRegistration, Abortable};
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, WebviewWindow};
+
+/// Prompt name that usage of `submit_prompt` is recorded under
+const DIRECT_PROMPT_NAME: &str = "Direct prompt";
 
 #[derive(Debug, Deserialize)]
 pub struct PromptRequest {
     prompt: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct RegisterLlmResult {
+    pub message: String,
+    /// Problems that didn't stop the config from being saved, e.g. an unknown model
+    pub warnings: Vec<String>,
+}
+
 #[tauri::command]
 pub async fn register_llm(
     state: State<'_, AppState>,
     config: ProviderConfig,
-) -> Result<String, String> {
-    println!("Registering LLM config: {:?}", config);
+    require_test: Option<bool>,
+) -> Result<RegisterLlmResult, String> {
+    println!("Registering LLM config: {}", config.name);
 
-    state
-        .register_llm(config)
+    let warnings = state
+        .register_llm(config, require_test.unwrap_or(false))
         .await
         .map_err(|e| e.to_string())?;
 
-    Ok("LLM configuration registered successfully".to_string())
+    Ok(RegisterLlmResult {
+        message: "LLM configuration registered successfully".to_string(),
+        warnings,
+    })
+}
+
+#[tauri::command]
+pub async fn delete_llm_config(state: State<'_, AppState>, name: String) -> Result<(), String> {
+    println!("Deleting LLM config: {}", name);
+    state
+        .delete_llm_config(&name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Send a probe request with a config, reporting latency and the model's reply
+#[tauri::command]
+pub async fn test_llm_config(
+    state: State<'_, AppState>,
+    config: ProviderConfig,
+) -> Result<ConfigTestResult, String> {
+    let config = state.with_probe_key(config).map_err(|e| e.to_string())?;
+    Ok(state.test_llm_config(&config).await)
+}
+
+/// List the models offered by a provider, for picking one in the settings
+#[tauri::command]
+pub async fn list_models(
+    state: State<'_, AppState>,
+    config: ProviderConfig,
+) -> Result<Vec<ModelInfo>, String> {
+    let config = state.with_probe_key(config).map_err(|e| e.to_string())?;
+    state.list_models(&config).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_llm_configs(state: State<'_, AppState>) -> Result<Vec<ProviderConfig>, String> {
+pub async fn get_llm_configs(state: State<'_, AppState>) -> Result<Vec<LlmConfigInfo>, String> {
     state.get_llm_configs().map_err(|e| e.to_string())
 }
 
@@ -35,16 +89,63 @@ pub async fn submit_prompt(
 ) -> Result<String, String> {
     println!("Received prompt request: {:?}", request);
 
+    let command = PromptCommand {
+        provider_name: config_name,
+        prompt: "{{selectedText}}".to_string(),
+        ..Default::default()
+    };
+
+    // Refuse prompts that won't fit rather than let the provider reject them
+    if let Some(check) = state
+        .preflight(&command, &request.prompt)
+        .map_err(|e| e.to_string())?
+    {
+        return Err(check.to_string());
+    }
+
     state
-        .submit_prompt(&config_name, request.prompt)
+        .submit_prompt(&command, DIRECT_PROMPT_NAME, request.prompt, &())
         .await
+        .map(|answer| answer.response)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_provider_groups(state: State<'_, AppState>) -> Result<Vec<ProviderGroup>, String> {
+    state
+        .settings_manager
+        .get_provider_groups()
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn register_provider_group(
+    state: State<'_, AppState>,
+    group: ProviderGroup,
+) -> Result<String, String> {
+    println!("Registering provider group: {:?}", group);
+
+    let name = group.name.clone();
+    state
+        .settings_manager
+        .add_provider_group(group)
+        .map_err(|e| e.to_string())?;
+
+    Ok(format!("Provider group '{}' registered successfully", name))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CustomPromptConfig {
     pub name: String,
     pub provider_name: String,
+    #[serde(default)]
+    pub fallback_providers: Vec<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub cache_ttl_secs: Option<u64>,
+    #[serde(default)]
+    pub chunking: Option<ChunkingConfig>,
     pub prompt_template: String,
     pub shortcut: String,
 }
@@ -61,10 +162,14 @@ pub async fn register_custom_prompt(
     let shortcut_config = ShortcutConfig {
         name: config.name.clone(),
         shortcut: config.shortcut,
-        command: CommandType::Prompt {
+        command: CommandType::Prompt(PromptCommand {
             provider_name: config.provider_name,
             prompt: config.prompt_template,
-        },
+            fallback_providers: config.fallback_providers,
+            system_prompt: config.system_prompt,
+            cache_ttl_secs: config.cache_ttl_secs,
+            chunking: config.chunking,
+        }),
     };
 
     // Update shortcuts to include this new custom prompt
@@ -112,15 +217,15 @@ pub async fn get_custom_prompts(
     let custom_prompts: Vec<CustomPromptConfig> = shortcuts
         .iter()
         .filter_map(|s| {
-            if let CommandType::Prompt {
-                provider_name,
-                prompt,
-            } = &s.command
-            {
+            if let CommandType::Prompt(command) = &s.command {
                 Some(CustomPromptConfig {
                     name: s.name.clone(),
-                    provider_name: provider_name.clone(),
-                    prompt_template: prompt.clone(),
+                    provider_name: command.provider_name.clone(),
+                    fallback_providers: command.fallback_providers.clone(),
+                    system_prompt: command.system_prompt.clone(),
+                    cache_ttl_secs: command.cache_ttl_secs,
+                    chunking: command.chunking.clone(),
+                    prompt_template: command.prompt.clone(),
                     shortcut: s.shortcut.clone(),
                 })
             } else {
@@ -134,16 +239,246 @@ pub async fn get_custom_prompts(
 
 #[derive(Debug, Serialize, Clone)]
 pub struct PromptResponse {
+    pub job_id: u64,
     pub prompt_name: String,
     pub response: String,
+    /// The provider that produced the response, which may be a fallback
+    pub provider_name: String,
+    /// Served from the response cache without a request
+    pub cached: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PromptResponseChunk {
+    pub job_id: u64,
+    pub prompt_name: String,
+    pub sequence: u64,
+    pub chunk: String,
 }
 
-/// Handle prompt command asynchronously
+/// Payload for the `prompt-started` and `prompt-cancelled` events
+#[derive(Debug, Serialize, Clone)]
+pub struct PromptJob {
+    pub job_id: u64,
+    pub prompt_name: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PromptRetry {
+    pub job_id: u64,
+    pub prompt_name: String,
+    #[serde(flatten)]
+    pub retry: RetryAttempt,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PromptFallback {
+    pub job_id: u64,
+    pub prompt_name: String,
+    pub failed_provider: String,
+    pub error: String,
+    pub next_provider: String,
+}
+
+/// Payload for the `prompt-progress` event of prompts run over a chunked selection
+#[derive(Debug, Serialize, Clone)]
+pub struct PromptProgress {
+    pub job_id: u64,
+    pub prompt_name: String,
+    #[serde(flatten)]
+    pub progress: ChunkProgress,
+}
+
+/// Payload for the `prompt-queued` event of requests held back by their
+/// provider's client-side limits
+#[derive(Debug, Serialize, Clone)]
+pub struct PromptQueued {
+    pub job_id: u64,
+    pub prompt_name: String,
+    #[serde(flatten)]
+    pub queued: QueuedRequest,
+}
+
+/// Payload for the `prompt-preflight` event, sent instead of running a prompt
+/// that would overflow its model's context window
+#[derive(Debug, Serialize, Clone)]
+pub struct PromptPreflight {
+    pub prompt_name: String,
+    pub message: String,
+    /// Job and message of a follow-up, which is sent again once confirmed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follow_up: Option<String>,
+    #[serde(flatten)]
+    pub check: PreflightCheck,
+}
+
+/// Ask the frontend to confirm a prompt that would overflow its model's
+/// context window
+fn emit_preflight<R: Runtime>(
+    app: &AppHandle<R>,
+    preflight: PromptPreflight,
+) -> Result<(), String> {
+    println!(
+        "Prompt '{}' needs confirmation: {}",
+        preflight.prompt_name, preflight.message
+    );
+    if let Some(main_window) = app.get_webview_window("main") {
+        main_window
+            .emit("prompt-preflight", preflight)
+            .map_err(|e| format!("Failed to emit prompt-preflight to main window: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Forwards the progress of a prompt job to the main window as events
+struct WindowListener<R: Runtime> {
+    window: Option<WebviewWindow<R>>,
+    job_id: u64,
+    prompt_name: String,
+    sequence: AtomicU64,
+}
+
+impl<R: Runtime> WindowListener<R> {
+    fn new(app: &AppHandle<R>, job_id: u64, prompt_name: &str) -> Self {
+        Self {
+            window: app.get_webview_window("main"),
+            job_id,
+            prompt_name: prompt_name.to_string(),
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(window) = &self.window {
+            if let Err(err) = window.emit(event, payload) {
+                println!("Failed to emit {}: {}", event, err);
+            }
+        }
+    }
+}
+
+impl<R: Runtime> PromptListener for WindowListener<R> {
+    fn on_chunk(&self, chunk: &str) {
+        self.emit(
+            "prompt-response-chunk",
+            PromptResponseChunk {
+                job_id: self.job_id,
+                prompt_name: self.prompt_name.clone(),
+                sequence: self.sequence.fetch_add(1, Ordering::Relaxed),
+                chunk: chunk.to_string(),
+            },
+        );
+    }
+
+    fn on_retry(&self, retry: &RetryAttempt) {
+        self.emit(
+            "prompt-retry",
+            PromptRetry {
+                job_id: self.job_id,
+                prompt_name: self.prompt_name.clone(),
+                retry: retry.clone(),
+            },
+        );
+    }
+
+    fn on_fallback(&self, failed_provider: &str, error: &str, next_provider: &str) {
+        self.emit(
+            "prompt-fallback",
+            PromptFallback {
+                job_id: self.job_id,
+                prompt_name: self.prompt_name.clone(),
+                failed_provider: failed_provider.to_string(),
+                error: error.to_string(),
+                next_provider: next_provider.to_string(),
+            },
+        );
+    }
+
+    fn on_progress(&self, progress: &ChunkProgress) {
+        self.emit(
+            "prompt-progress",
+            PromptProgress {
+                job_id: self.job_id,
+                prompt_name: self.prompt_name.clone(),
+                progress: progress.clone(),
+            },
+        );
+    }
+
+    fn on_queued(&self, queued: &QueuedRequest) {
+        self.emit(
+            "prompt-queued",
+            PromptQueued {
+                job_id: self.job_id,
+                prompt_name: self.prompt_name.clone(),
+                queued: queued.clone(),
+            },
+        );
+    }
+}
+
+/// Wait for a prompt job to finish. Returns `None` if the job was cancelled,
+/// in which case its result must be discarded.
+async fn await_job<T>(
+    state: &AppState,
+    job_id: u64,
+    registration: AbortRegistration,
+    job: impl Future<Output = T>,
+) -> Result<Option<T>, String> {
+    let result = Abortable::new(job, registration).await;
+
+    // A job cancelled after its response arrived must not be stored either
+    let still_active = state.finish_job(job_id).map_err(|e| e.to_string())?;
+    let Ok(result) = result else {
+        println!("Prompt job {} was cancelled", job_id);
+        return Ok(None);
+    };
+    if !still_active {
+        println!("Discarding result of cancelled prompt job {}", job_id);
+        return Ok(None);
+    }
+
+    Ok(Some(result))
+}
+
+/// Store a finished response for PasteOutput and send it to the frontend
+async fn publish_response<R: Runtime>(
+    state: &AppState,
+    listener: &WindowListener<R>,
+    answer: PromptAnswer,
+) -> Result<(), String> {
+    // Store the latest output for PasteOutput command
+    if let Err(err) = state.set_latest_output(answer.response.clone()).await {
+        println!("Error storing latest output: {}", err);
+    }
+
+    // Emit the response to the frontend
+    let prompt_response = PromptResponse {
+        job_id: listener.job_id,
+        prompt_name: listener.prompt_name.clone(),
+        response: answer.response,
+        provider_name: answer.provider_name,
+        cached: answer.cached,
+    };
+    if let Some(main_window) = &listener.window {
+        main_window
+            .emit("prompt-response", prompt_response)
+            .map_err(|e| format!("Failed to emit prompt-response to main window: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Handle prompt command asynchronously. Unless `confirmed`, a prompt that
+/// would overflow its model's context window is not sent; the frontend is
+/// asked for confirmation with a `prompt-preflight` event instead.
 async fn handle_prompt_command<R: Runtime>(
     app: &AppHandle<R>,
-    provider_name: &str,
-    prompt: &str,
+    command: &PromptCommand,
     prompt_name: &str,
+    confirmed: bool,
 ) -> Result<(), String> {
     let state = app.state::<AppState>();
 
@@ -163,30 +498,53 @@ async fn handle_prompt_command<R: Runtime>(
         return Ok(());
     }
 
-    // Replace {{selectedText}} placeholder in the prompt template
-    let final_prompt = prompt.replace("{{selectedText}}", &selected_text);
+    if !confirmed {
+        let check = state
+            .preflight(command, &selected_text)
+            .map_err(|e| e.to_string())?;
+        if let Some(check) = check {
+            let preflight = PromptPreflight {
+                prompt_name: prompt_name.to_string(),
+                message: check.to_string(),
+                job_id: None,
+                follow_up: None,
+                check,
+            };
+            return emit_preflight(app, preflight);
+        }
+    }
+
+    // Register the job so it can be cancelled while in flight
+    let (job_id, registration) = state.start_job(prompt_name).map_err(|e| e.to_string())?;
+    let listener = WindowListener::new(app, job_id, prompt_name);
+    listener.emit(
+        "prompt-started",
+        PromptJob {
+            job_id,
+            prompt_name: prompt_name.to_string(),
+        },
+    );
 
-    // Submit the prompt to the LLM provider
-    let result = state.submit_prompt(provider_name, final_prompt).await;
+    // Submit the prompt to the LLM provider, forwarding progress as it happens
+    let submission = state.submit_selection(command, prompt_name, &selected_text, &listener);
+    let Some(result) = await_job(&state, job_id, registration, submission).await? else {
+        return Ok(());
+    };
 
     match result {
-        Ok(response) => {
-            // Store the latest output for PasteOutput command
-            if let Err(err) = state.set_latest_output(response.clone()).await {
-                println!("Error storing latest output: {}", err);
-            }
-            // Emit the response to the frontend
-            let prompt_response = PromptResponse {
-                prompt_name: prompt_name.to_string(),
-                response,
-            };
-            if let Some(main_window) = app.get_webview_window("main") {
-                main_window
-                    .emit("prompt-response", prompt_response)
-                    .map_err(|e| format!("Failed to emit prompt-response to main window: {}", e))?;
+        Ok((final_prompt, answer)) => {
+            // Keep the transcript so the response can be refined with follow-ups
+            if let Err(err) = state.start_conversation(
+                job_id,
+                prompt_name,
+                command.system_prompt.clone(),
+                final_prompt,
+                &answer,
+            ) {
+                println!("Error storing conversation: {}", err);
             }
 
-            Ok(())
+            publish_response(&state, &listener, answer).await
         }
         Err(err) => {
             println!("Error: {:?}", err);
@@ -195,12 +553,85 @@ async fn handle_prompt_command<R: Runtime>(
     }
 }
 
+/// Continue an earlier prompt execution with a follow-up message such as
+/// "shorter" or "as bullet points", sending the whole transcript back to the
+/// provider that answered it. As with prompts, a follow-up that would overflow
+/// the context window waits for confirmation unless `confirmed`.
+#[tauri::command]
+pub async fn follow_up<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    job_id: u64,
+    message: String,
+    confirmed: Option<bool>,
+) -> Result<(), String> {
+    if message.trim().is_empty() {
+        return Err("Follow-up message is empty".to_string());
+    }
+
+    let conversation = state.get_conversation(job_id).map_err(|e| e.to_string())?;
+
+    if !confirmed.unwrap_or(false) {
+        let check = state
+            .preflight_follow_up(&conversation, &message)
+            .map_err(|e| e.to_string())?;
+        if let Some(check) = check {
+            let preflight = PromptPreflight {
+                prompt_name: conversation.prompt_name.clone(),
+                message: check.to_string(),
+                job_id: Some(job_id),
+                follow_up: Some(message),
+                check,
+            };
+            return emit_preflight(&app, preflight);
+        }
+    }
+
+    // Follow-ups run under the original job id, so they can be cancelled the same way
+    let registration = state
+        .resume_job(job_id, &conversation.prompt_name)
+        .map_err(|e| e.to_string())?;
+    let listener = WindowListener::new(&app, job_id, &conversation.prompt_name);
+    listener.emit(
+        "prompt-started",
+        PromptJob {
+            job_id,
+            prompt_name: conversation.prompt_name.clone(),
+        },
+    );
+
+    let submission = state.follow_up(&conversation, &message, &listener);
+    let Some(result) = await_job(&state, job_id, registration, submission).await? else {
+        return Ok(());
+    };
+
+    match result {
+        Ok(response) => {
+            state
+                .add_follow_up(job_id, message, response.clone())
+                .map_err(|e| e.to_string())?;
+
+            let answer = PromptAnswer {
+                response,
+                provider_name: conversation.provider_name,
+                cached: false,
+            };
+            publish_response(&state, &listener, answer).await
+        }
+        Err(err) => {
+            println!("Error: {:?}", err);
+            Err(format!("Error processing follow-up: {}", err))
+        }
+    }
+}
+
 /// Execute a custom prompt from the frontend
 #[tauri::command]
 pub async fn execute_custom_prompt<R: Runtime>(
     app: AppHandle<R>,
     state: tauri::State<'_, AppState>,
     prompt_name: String,
+    confirmed: Option<bool>,
 ) -> Result<(), String> {
     let shortcuts = state
         .settings_manager
@@ -214,14 +645,37 @@ pub async fn execute_custom_prompt<R: Runtime>(
         .ok_or_else(|| format!("Custom prompt '{}' not found", prompt_name))?;
 
     // Verify it's actually a Prompt command
-    if let CommandType::Prompt {
-        provider_name,
-        prompt,
-    } = &prompt_shortcut.command
-    {
+    if let CommandType::Prompt(command) = &prompt_shortcut.command {
         // Execute the prompt command asynchronously
-        handle_prompt_command(&app, provider_name, prompt, &prompt_name).await
+        handle_prompt_command(&app, command, &prompt_name, confirmed.unwrap_or(false)).await
     } else {
         Err(format!("'{}' is not a custom prompt", prompt_name))
     }
 }
+
+/// Cancel an in-flight prompt execution
+#[tauri::command]
+pub async fn cancel_prompt<R: Runtime>(
+    app: AppHandle<R>,
+    state: tauri::State<'_, AppState>,
+    job_id: u64,
+) -> Result<(), String> {
+    let job = state
+        .cancel_job(job_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No running prompt with job id {}", job_id))?;
+
+    if let Some(main_window) = app.get_webview_window("main") {
+        main_window
+            .emit(
+                "prompt-cancelled",
+                PromptJob {
+                    job_id,
+                    prompt_name: job.prompt_name,
+                },
+            )
+            .map_err(|e| format!("Failed to emit prompt-cancelled to main window: {}", e))?;
+    }
+
+    Ok(())
+}
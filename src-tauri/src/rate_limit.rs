@@ -0,0 +1,346 @@
+use crate::settings::ProviderConfig;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Requests and tokens are counted over a sliding minute
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// The limit that holds a request back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    RequestsPerMinute,
+    TokensPerMinute,
+    Concurrency,
+}
+
+/// Report for a request waiting for its provider's limits
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedRequest {
+    pub provider_name: String,
+    pub limit: LimitKind,
+    /// Estimated wait, unknown while waiting for a running request to finish
+    pub wait_ms: Option<u64>,
+}
+
+/// A request sent within the last minute
+#[derive(Debug)]
+struct SentRequest {
+    id: u64,
+    sent_at: Instant,
+    tokens: u64,
+}
+
+#[derive(Debug, Default)]
+struct ProviderState {
+    sent: VecDeque<SentRequest>,
+    in_flight: u32,
+}
+
+impl ProviderState {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .sent
+            .front()
+            .is_some_and(|request| request.sent_at + WINDOW <= now)
+        {
+            self.sent.pop_front();
+        }
+    }
+
+    /// The limit a request of `tokens` tokens would exceed if sent now, with
+    /// the time until it no longer does
+    fn blocker(
+        &self,
+        config: &ProviderConfig,
+        tokens: u64,
+        now: Instant,
+    ) -> Option<(LimitKind, Option<Duration>)> {
+        if config
+            .max_concurrent
+            .is_some_and(|max| max > 0 && self.in_flight >= max)
+        {
+            return Some((LimitKind::Concurrency, None));
+        }
+
+        if let Some(rpm) = config.requests_per_minute.filter(|rpm| *rpm > 0) {
+            let rpm = rpm as usize;
+            if self.sent.len() >= rpm {
+                let expires = self.sent[self.sent.len() - rpm].sent_at + WINDOW;
+                return Some((
+                    LimitKind::RequestsPerMinute,
+                    Some(expires.saturating_duration_since(now)),
+                ));
+            }
+        }
+
+        if let Some(tpm) = config.tokens_per_minute.filter(|tpm| *tpm > 0) {
+            let used: u64 = self.sent.iter().map(|request| request.tokens).sum();
+            // A request larger than the whole budget goes once the minute is clear
+            if !self.sent.is_empty() && used + tokens > tpm {
+                let mut freed = 0;
+                let expires = self
+                    .sent
+                    .iter()
+                    .find(|request| {
+                        freed += request.tokens;
+                        used - freed + tokens <= tpm
+                    })
+                    .or(self.sent.back())
+                    .map(|request| request.sent_at + WINDOW)?;
+                return Some((
+                    LimitKind::TokensPerMinute,
+                    Some(expires.saturating_duration_since(now)),
+                ));
+            }
+        }
+
+        None
+    }
+}
+
+/// Whether the config caps its request rate, token rate or concurrency
+pub fn is_limited(config: &ProviderConfig) -> bool {
+    config.requests_per_minute.is_some()
+        || config.tokens_per_minute.is_some()
+        || config.max_concurrent.is_some()
+}
+
+/// Client-side limits per config, so free-tier keys aren't throttled by the
+/// provider. Requests over a limit wait instead of failing.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    providers: Mutex<HashMap<String, ProviderState>>,
+    /// Signalled whenever a request finishes or its token count drops
+    released: Notify,
+    next_id: AtomicU64,
+}
+
+impl RateLimiter {
+    /// Wait until a request of about `tokens` tokens fits the config's limits,
+    /// reporting every wait. Returns `None` for configs without limits,
+    /// otherwise a permit that counts as in flight until dropped.
+    pub async fn acquire(
+        &self,
+        config: &ProviderConfig,
+        tokens: u64,
+        on_wait: impl Fn(&QueuedRequest),
+    ) -> Result<Option<Permit<'_>>, BoxError> {
+        if !is_limited(config) {
+            return Ok(None);
+        }
+
+        loop {
+            // Subscribe before checking, so a release in between isn't missed
+            let released = self.released.notified();
+            let (limit, wait) = {
+                let mut providers = self.providers.lock().map_err(|e| e.to_string())?;
+                let state = providers.entry(config.name.clone()).or_default();
+                let now = Instant::now();
+                state.prune(now);
+
+                match state.blocker(config, tokens, now) {
+                    Some(blocker) => blocker,
+                    None => {
+                        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                        state.sent.push_back(SentRequest {
+                            id,
+                            sent_at: now,
+                            tokens,
+                        });
+                        state.in_flight += 1;
+                        return Ok(Some(Permit {
+                            limiter: self,
+                            provider_name: config.name.clone(),
+                            id,
+                        }));
+                    }
+                }
+            };
+
+            on_wait(&QueuedRequest {
+                provider_name: config.name.clone(),
+                limit,
+                wait_ms: wait.map(|wait| wait.as_millis() as u64),
+            });
+            match wait {
+                Some(wait) => {
+                    let _ = tokio::time::timeout(wait, released).await;
+                }
+                None => released.await,
+            }
+        }
+    }
+}
+
+/// A request admitted by the rate limiter
+pub struct Permit<'a> {
+    limiter: &'a RateLimiter,
+    provider_name: String,
+    id: u64,
+}
+
+impl Permit<'_> {
+    /// Replace the request's token estimate with the usage it actually had
+    pub fn record_tokens(&self, tokens: u64) {
+        if let Ok(mut providers) = self.limiter.providers.lock() {
+            let request = providers
+                .get_mut(&self.provider_name)
+                .and_then(|state| state.sent.iter_mut().find(|r| r.id == self.id));
+            if let Some(request) = request {
+                request.tokens = tokens;
+            }
+        }
+        self.limiter.released.notify_waiters();
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut providers) = self.limiter.providers.lock() {
+            if let Some(state) = providers.get_mut(&self.provider_name) {
+                state.in_flight = state.in_flight.saturating_sub(1);
+            }
+        }
+        self.limiter.released.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A moment at least a window after the clock's start, so requests can
+    /// be placed up to a minute before it
+    fn later() -> Instant {
+        Instant::now() + WINDOW
+    }
+
+    /// State with requests of the given tokens sent the given seconds ago
+    fn state(now: Instant, sent: &[(u64, u64)]) -> ProviderState {
+        ProviderState {
+            sent: sent
+                .iter()
+                .enumerate()
+                .map(|(id, &(secs_ago, tokens))| SentRequest {
+                    id: id as u64,
+                    sent_at: now - Duration::from_secs(secs_ago),
+                    tokens,
+                })
+                .collect(),
+            in_flight: 0,
+        }
+    }
+
+    fn limits(rpm: Option<u32>, tpm: Option<u64>, concurrent: Option<u32>) -> ProviderConfig {
+        ProviderConfig {
+            name: "groq".to_string(),
+            requests_per_minute: rpm,
+            tokens_per_minute: tpm,
+            max_concurrent: concurrent,
+            ..Default::default()
+        }
+    }
+
+    fn wait(secs: u64) -> Option<Duration> {
+        Some(Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn blocks_at_max_concurrency_without_an_estimate() {
+        let now = later();
+        let mut state = state(now, &[]);
+        state.in_flight = 2;
+        let config = limits(None, None, Some(2));
+        assert_eq!(
+            state.blocker(&config, 0, now),
+            Some((LimitKind::Concurrency, None))
+        );
+        assert_eq!(state.blocker(&limits(None, None, Some(3)), 0, now), None);
+        assert_eq!(state.blocker(&limits(None, None, Some(0)), 0, now), None);
+    }
+
+    #[test]
+    fn waits_for_the_oldest_request_in_the_window() {
+        let now = later();
+        let config = limits(Some(2), None, None);
+        let mut state = state(now, &[(70, 0), (50, 0), (10, 0)]);
+        state.prune(now);
+        assert_eq!(state.sent.len(), 2);
+        assert_eq!(
+            state.blocker(&config, 0, now),
+            Some((LimitKind::RequestsPerMinute, wait(10)))
+        );
+        assert_eq!(state.blocker(&limits(Some(3), None, None), 0, now), None);
+    }
+
+    #[test]
+    fn waits_until_enough_tokens_are_freed() {
+        let now = later();
+        let config = limits(None, Some(1000), None);
+        let state = state(now, &[(50, 400), (20, 400)]);
+        assert_eq!(state.blocker(&config, 200, now), None);
+        assert_eq!(
+            state.blocker(&config, 300, now),
+            Some((LimitKind::TokensPerMinute, wait(10)))
+        );
+        assert_eq!(
+            state.blocker(&config, 700, now),
+            Some((LimitKind::TokensPerMinute, wait(40)))
+        );
+    }
+
+    #[test]
+    fn sends_a_request_over_the_whole_budget_once_the_minute_is_clear() {
+        let now = later();
+        let config = limits(None, Some(1000), None);
+        assert_eq!(state(now, &[]).blocker(&config, 5000, now), None);
+        assert_eq!(
+            state(now, &[(50, 100), (30, 100)]).blocker(&config, 5000, now),
+            Some((LimitKind::TokensPerMinute, wait(30)))
+        );
+    }
+
+    #[test]
+    fn records_actual_tokens_and_releases_on_drop() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let limiter = RateLimiter::default();
+        let config = limits(None, Some(1000), Some(1));
+
+        runtime.block_on(async {
+            assert!(limiter
+                .acquire(&limits(None, None, None), 0, |_| {})
+                .await
+                .unwrap()
+                .is_none());
+
+            let permit = limiter.acquire(&config, 900, |_| {}).await.unwrap();
+            let permit = permit.unwrap();
+            permit.record_tokens(100);
+            {
+                let providers = limiter.providers.lock().unwrap();
+                let state = &providers["groq"];
+                assert_eq!(state.sent[0].tokens, 100);
+                assert_eq!(state.in_flight, 1);
+                let now = Instant::now();
+                assert_eq!(
+                    state.blocker(&limits(None, Some(1000), None), 800, now),
+                    None
+                );
+            }
+            drop(permit);
+
+            // The finished request still counts towards the minute's tokens
+            let permit = limiter.acquire(&config, 800, |_| {}).await.unwrap();
+            assert!(permit.is_some());
+            let providers = limiter.providers.lock().unwrap();
+            assert_eq!(providers["groq"].sent.len(), 2);
+        });
+    }
+}
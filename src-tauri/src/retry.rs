@@ -0,0 +1,354 @@
+use crate::settings::ProviderConfig;
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+/// Transient failures are retried a couple of times unless the config says otherwise
+const DEFAULT_MAX_RETRIES: u32 = 2;
+const DEFAULT_BACKOFF_MS: u64 = 1000;
+const DEFAULT_MAX_BACKOFF_MS: u64 = 30_000;
+
+/// Retry settings for a provider, with defaults filled in
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &ProviderConfig) -> Self {
+        Self {
+            max_retries: config.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            initial_backoff: Duration::from_millis(
+                config.retry_backoff_ms.unwrap_or(DEFAULT_BACKOFF_MS),
+            ),
+            max_backoff: Duration::from_millis(
+                config
+                    .retry_backoff_max_ms
+                    .unwrap_or(DEFAULT_MAX_BACKOFF_MS),
+            ),
+        }
+    }
+
+    /// Exponential backoff for the given retry attempt (starting at 1)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    /// Delay before the given retry attempt: the one the provider asked for,
+    /// capped at the maximum backoff, or else the exponential backoff
+    pub fn delay(&self, attempt: u32, err: &(dyn std::error::Error + 'static)) -> Duration {
+        retry_after(err)
+            .map(|delay| delay.min(self.max_backoff))
+            .unwrap_or_else(|| self.backoff(attempt))
+    }
+}
+
+/// Progress report for a request that is about to be retried
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryAttempt {
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub delay_ms: u64,
+    pub error: String,
+}
+
+/// HTTP-level failure from a provider, keeping the details needed to decide
+/// whether and when to retry
+#[derive(Debug)]
+pub struct ProviderError {
+    pub message: String,
+    pub status: Option<u16>,
+    pub retry_after: Option<Duration>,
+}
+
+impl ProviderError {
+    pub fn from_response(
+        provider: &str,
+        status: reqwest::StatusCode,
+        headers: &reqwest::header::HeaderMap,
+        body: &str,
+    ) -> Self {
+        Self {
+            message: format!("{} returned {}: {}", provider, status, body),
+            status: Some(status.as_u16()),
+            retry_after: parse_retry_after(headers),
+        }
+    }
+
+    pub fn timeout(secs: u64) -> Self {
+        Self {
+            message: format!("Request timed out after {} seconds", secs),
+            status: Some(408),
+            retry_after: None,
+        }
+    }
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+/// Read the delay requested by `Retry-After` (seconds or an HTTP date) or
+/// OpenAI's `retry-after-ms`
+pub fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.trim().parse::<u64>().ok()) {
+        return Some(Duration::from_millis(ms));
+    }
+    let value = header("retry-after")?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    // A date in the past means the request can go right away
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 425 | 429 | 500 | 502 | 503 | 504)
+}
+
+/// Status codes an error message mentions as such, like `status 429`,
+/// `HTTP 503` or `(500 Internal Server Error)`. Other numbers, such as token
+/// counts, are ignored.
+fn mentioned_statuses(message: &str) -> Vec<u16> {
+    let mut statuses = Vec::new();
+    let mut previous = "";
+    for word in message.split(|c: char| c.is_whitespace() || c == '=') {
+        let code = word
+            .trim_start_matches('(')
+            .trim_end_matches([')', ':', ',', '.']);
+        let in_context = word.starts_with('(')
+            || previous.starts_with("http/")
+            || matches!(
+                previous.trim_end_matches(':'),
+                "status" | "http" | "code" | "returned"
+            );
+        if in_context && code.len() == 3 {
+            statuses.extend(code.parse::<u16>().ok());
+        }
+        if !word.is_empty() {
+            previous = word;
+        }
+    }
+    statuses
+}
+
+/// Whether a failed request is worth retrying. Errors from the `llm` crate only
+/// carry a message, so their status code is recovered from the text.
+pub fn is_retryable(err: &(dyn std::error::Error + 'static)) -> bool {
+    if let Some(err) = err.downcast_ref::<ProviderError>() {
+        return err.status.is_some_and(is_retryable_status);
+    }
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        return err.is_timeout()
+            || err.is_connect()
+            || err
+                .status()
+                .is_some_and(|s| is_retryable_status(s.as_u16()));
+    }
+
+    let message = err.to_string().to_lowercase();
+    message.contains("rate limit")
+        || message.contains("timed out")
+        || message.contains("overloaded")
+        || mentioned_statuses(&message)
+            .into_iter()
+            .any(is_retryable_status)
+}
+
+/// Delay requested by the provider, if the backend exposed one
+pub fn retry_after(err: &(dyn std::error::Error + 'static)) -> Option<Duration> {
+    err.downcast_ref::<ProviderError>()
+        .and_then(|err| err.retry_after)
+}
+
+/// Broad cause of a failed request, for explaining it to the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    Auth,
+    UnknownModel,
+    Network,
+    Quota,
+    Other,
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            FailureKind::Auth => "authentication failed",
+            FailureKind::UnknownModel => "unknown model",
+            FailureKind::Network => "network error",
+            FailureKind::Quota => "quota or rate limit exceeded",
+            FailureKind::Other => "request failed",
+        };
+        write!(f, "{}", description)
+    }
+}
+
+/// Classify a failed request. As with `is_retryable`, errors from the `llm`
+/// crate are classified by their message.
+pub fn classify_failure(err: &(dyn std::error::Error + 'static)) -> FailureKind {
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        if err.is_connect() || err.is_timeout() || err.is_request() {
+            return FailureKind::Network;
+        }
+    }
+
+    let message = err.to_string().to_lowercase();
+    let status = err.downcast_ref::<ProviderError>().and_then(|e| e.status);
+    let mentions = |words: &[&str]| words.iter().any(|w| message.contains(w));
+
+    // Providers disagree on status codes, e.g. Google answers a bad key with 400
+    if matches!(status, Some(401 | 403))
+        || mentions(&[
+            "unauthorized",
+            "api key",
+            "api_key",
+            "authentication",
+            "permission",
+        ])
+    {
+        FailureKind::Auth
+    } else if matches!(status, Some(402 | 429))
+        || mentions(&["quota", "rate limit", "insufficient", "billing", "credit"])
+    {
+        FailureKind::Quota
+    } else if mentions(&["model"])
+        && (status == Some(404) || mentions(&["not found", "does not exist", "unknown", "invalid"]))
+    {
+        FailureKind::UnknownModel
+    } else if status == Some(408) || mentions(&["timed out", "connection", "dns", "network"]) {
+        FailureKind::Network
+    } else {
+        FailureKind::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    fn message_error(message: &str) -> Box<dyn std::error::Error + Send + Sync> {
+        message.into()
+    }
+
+    fn retryable(message: &str) -> bool {
+        is_retryable(message_error(message).as_ref())
+    }
+
+    #[test]
+    fn retries_status_codes_in_context() {
+        assert!(retryable(
+            "OpenAI API returned error status: 429 Too Many Requests"
+        ));
+        assert!(retryable("HTTP 503 Service Unavailable"));
+        assert!(retryable("HTTP/1.1 502 Bad Gateway"));
+        assert!(retryable(
+            "HTTP status server error (500 Internal Server Error) for url (https://x)"
+        ));
+        assert!(retryable("request failed with status=504"));
+        assert!(retryable("Provider is overloaded"));
+        assert!(retryable("Rate limit reached for requests"));
+    }
+
+    #[test]
+    fn ignores_unrelated_numbers() {
+        assert!(!retryable(
+            "This model's maximum context length is 500 tokens"
+        ));
+        assert!(!retryable("max_tokens must be below 429"));
+        assert!(!retryable("Invalid model gpt-503-turbo"));
+        assert!(!retryable("HTTP 401 Unauthorized"));
+        assert!(!retryable("status: 400 Bad Request"));
+    }
+
+    #[test]
+    fn uses_provider_error_status() {
+        let err = ProviderError {
+            message: "500 tokens".to_string(),
+            status: Some(400),
+            retry_after: None,
+        };
+        assert!(!is_retryable(&err));
+        assert!(is_retryable(&ProviderError::timeout(30)));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds_and_milliseconds() {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("7"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert("retry-after-ms", HeaderValue::from_static("250"));
+        assert_eq!(
+            parse_retry_after(&headers),
+            Some(Duration::from_millis(250))
+        );
+    }
+
+    #[test]
+    fn parses_retry_after_http_date() {
+        let date = (chrono::Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_str(&date).unwrap());
+        let delay = parse_retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(110) && delay <= Duration::from_secs(120));
+
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert("retry-after", HeaderValue::from_static("soon"));
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn retries_twice_unless_configured() {
+        let policy = RetryPolicy::from_config(&ProviderConfig::default());
+        assert_eq!(policy.max_retries, 2);
+
+        let config = ProviderConfig {
+            max_retries: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(RetryPolicy::from_config(&config).max_retries, 0);
+    }
+
+    #[test]
+    fn caps_requested_delay_at_max_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(10),
+        };
+        let err = ProviderError {
+            message: "slow down".to_string(),
+            status: Some(429),
+            retry_after: Some(Duration::from_secs(3600)),
+        };
+        assert_eq!(policy.delay(1, &err), Duration::from_secs(10));
+        assert_eq!(
+            policy.delay(2, message_error("HTTP 503").as_ref()),
+            Duration::from_secs(2)
+        );
+    }
+}
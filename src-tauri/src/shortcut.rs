@@ -17,7 +17,7 @@ pub fn enable_shortcuts(app: &App) {
         .filter(|s| s.shortcut != "")
         .filter(|s| {
             // Only include non-custom-prompt shortcuts for Tauri global shortcut registration
-            !matches!(s.command, CommandType::Prompt { .. })
+            !matches!(s.command, CommandType::Prompt(_))
         })
         .map(|s| {
             (
@@ -89,10 +89,7 @@ fn handle_shortcut_commands<R: Runtime>(app: &AppHandle<R>, command: &CommandTyp
                 println!("No response available to paste (no response found)");
             }
         }
-        CommandType::Prompt {
-            provider_name: _,
-            prompt: _,
-        } => {
+        CommandType::Prompt(_) => {
             // Pass since prompt command is no longer handled by global shortcut plugin
         }
     }
@@ -176,7 +173,7 @@ pub async fn update_shortcut<R: Runtime>(
         .find(|s| s.name == shortcut_config.name)
     {
         // Unregister old shortcut if it was enabled and not a custom prompt
-        if existing.shortcut != "" && !matches!(existing.command, CommandType::Prompt { .. }) {
+        if existing.shortcut != "" && !matches!(existing.command, CommandType::Prompt(_)) {
             let old_shortcut = existing
                 .shortcut
                 .parse::<Shortcut>()
@@ -198,7 +195,7 @@ pub async fn update_shortcut<R: Runtime>(
         .map_err(|e| e.to_string())?;
 
     // Only register the shortcut with the global shortcut system if it's not a custom prompt
-    if !matches!(shortcut_config.command, CommandType::Prompt { .. }) {
+    if !matches!(shortcut_config.command, CommandType::Prompt(_)) {
         app.global_shortcut()
             .on_shortcut(shortcut, move |app, scut, event| {
                 if scut == &shortcut {
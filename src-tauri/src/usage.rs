@@ -0,0 +1,377 @@
+use crate::settings::{AppState, ModelPrice};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use llm::chat::ChatMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{Manager, State};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Token counts for a single request
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Set when the backend didn't report usage and the counts were estimated
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    /// Estimate usage for backends that don't report it
+    pub fn estimate(system: Option<&str>, messages: &[ChatMessage], response: &str) -> Self {
+        let input = system.map(estimate_tokens).unwrap_or(0)
+            + messages
+                .iter()
+                .map(|m| estimate_tokens(&m.content))
+                .sum::<u64>();
+
+        Self {
+            input_tokens: input,
+            output_tokens: estimate_tokens(response),
+            estimated: true,
+        }
+    }
+}
+
+/// Rough token count, at about four characters per token for English text
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Usage of one successful request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Name of the LLM configuration that served the request
+    pub provider_name: String,
+    pub provider: String,
+    pub model: String,
+    pub prompt_name: String,
+    /// Key that served the request, redacted, for configs with a key pool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(flatten)]
+    pub usage: TokenUsage,
+}
+
+/// Append-only log of token usage, stored as one JSON record per line next
+/// to the settings file
+pub struct UsageLog {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl UsageLog {
+    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_dir = app_handle.path().app_config_dir()?;
+        fs::create_dir_all(&config_dir)?;
+
+        Ok(Self {
+            path: config_dir.join("usage.jsonl"),
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn record(&self, record: &UsageRecord) -> Result<(), BoxError> {
+        let line = serde_json::to_string(record)?;
+
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    pub fn load(&self) -> Result<Vec<UsageRecord>, BoxError> {
+        let _guard = self.lock.lock().map_err(|e| e.to_string())?;
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        // Skip lines that can't be parsed, such as one cut short by a crash
+        let contents = fs::read_to_string(&self.path)?;
+        Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// Inclusive range of local dates to report on; a missing end is unbounded
+#[derive(Debug, Default, Deserialize)]
+pub struct UsageRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl UsageRange {
+    fn contains(&self, date: NaiveDate) -> bool {
+        !matches!(self.from, Some(from) if date < from) && !matches!(self.to, Some(to) if date > to)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Requests whose token counts were estimated
+    pub estimated_requests: u64,
+    /// Cost in USD of the requests with a known price
+    pub cost: f64,
+    /// Requests for models missing from the price table
+    pub unpriced_requests: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &TokenUsage, cost: Option<f64>) {
+        self.requests += 1;
+        self.input_tokens += usage.input_tokens;
+        self.output_tokens += usage.output_tokens;
+        if usage.estimated {
+            self.estimated_requests += 1;
+        }
+        match cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced_requests += 1,
+        }
+    }
+}
+
+/// Totals for one day, month, model or prompt
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageGroup {
+    pub name: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub total: UsageTotals,
+    /// Per day (`YYYY-MM-DD`), oldest first
+    pub daily: Vec<UsageGroup>,
+    /// Per month (`YYYY-MM`), oldest first
+    pub monthly: Vec<UsageGroup>,
+    /// Per configuration and model, most expensive first
+    pub models: Vec<UsageGroup>,
+    /// Per prompt, most expensive first
+    pub prompts: Vec<UsageGroup>,
+}
+
+/// Cost of a request in USD, if its model has a price. As with context
+/// windows, prices match model names by prefix, so `gpt-4o` also covers
+/// `gpt-4o-2024-08-06`, and the longest matching entry wins.
+fn request_cost(record: &UsageRecord, prices: &[ModelPrice]) -> Option<f64> {
+    let price = prices
+        .iter()
+        .filter(|p| {
+            p.provider.eq_ignore_ascii_case(&record.provider) && record.model.starts_with(&p.model)
+        })
+        .max_by_key(|p| p.model.len())?;
+
+    Some(
+        (record.usage.input_tokens as f64 * price.input_per_million
+            + record.usage.output_tokens as f64 * price.output_per_million)
+            / 1_000_000.0,
+    )
+}
+
+/// Aggregate usage records into a report. Costs use the current price table,
+/// so prices added later also apply to earlier usage.
+pub fn build_report(
+    records: &[UsageRecord],
+    prices: &[ModelPrice],
+    range: &UsageRange,
+) -> UsageReport {
+    let mut total = UsageTotals::default();
+    let mut daily: HashMap<String, UsageTotals> = HashMap::new();
+    let mut monthly: HashMap<String, UsageTotals> = HashMap::new();
+    let mut models: HashMap<String, UsageTotals> = HashMap::new();
+    let mut prompts: HashMap<String, UsageTotals> = HashMap::new();
+
+    for record in records {
+        let local = record.timestamp.with_timezone(&Local);
+        if !range.contains(local.date_naive()) {
+            continue;
+        }
+
+        let cost = request_cost(record, prices);
+        let groups = [
+            (&mut daily, local.format("%Y-%m-%d").to_string()),
+            (&mut monthly, local.format("%Y-%m").to_string()),
+            (
+                &mut models,
+                format!("{} ({})", record.provider_name, record.model),
+            ),
+            (&mut prompts, record.prompt_name.clone()),
+        ];
+
+        total.add(&record.usage, cost);
+        for (group, name) in groups {
+            group.entry(name).or_default().add(&record.usage, cost);
+        }
+    }
+
+    let by_name = |groups: HashMap<String, UsageTotals>| {
+        let mut groups: Vec<UsageGroup> = groups
+            .into_iter()
+            .map(|(name, totals)| UsageGroup { name, totals })
+            .collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        groups
+    };
+    let by_cost = |groups: HashMap<String, UsageTotals>| {
+        let mut groups = by_name(groups);
+        groups.sort_by(|a, b| b.totals.cost.total_cmp(&a.totals.cost));
+        groups
+    };
+
+    UsageReport {
+        total,
+        daily: by_name(daily),
+        monthly: by_name(monthly),
+        models: by_cost(models),
+        prompts: by_cost(prompts),
+    }
+}
+
+#[tauri::command]
+pub async fn get_usage_report(
+    state: State<'_, AppState>,
+    range: Option<UsageRange>,
+) -> Result<UsageReport, String> {
+    let records = state.usage_log.load().map_err(|e| e.to_string())?;
+    let prices = state
+        .settings_manager
+        .get_model_prices()
+        .map_err(|e| e.to_string())?;
+
+    Ok(build_report(&records, &prices, &range.unwrap_or_default()))
+}
+
+#[tauri::command]
+pub async fn get_model_prices(state: State<'_, AppState>) -> Result<Vec<ModelPrice>, String> {
+    state
+        .settings_manager
+        .get_model_prices()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_model_prices(
+    state: State<'_, AppState>,
+    prices: Vec<ModelPrice>,
+) -> Result<(), String> {
+    state
+        .settings_manager
+        .update_model_prices(prices)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn price(provider: &str, model: &str, input: f64, output: f64) -> ModelPrice {
+        ModelPrice {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            input_per_million: input,
+            output_per_million: output,
+        }
+    }
+
+    /// A request made at noon local time on the given day
+    fn record(day: u32, model: &str, prompt: &str, input: u64, output: u64) -> UsageRecord {
+        UsageRecord {
+            timestamp: Local
+                .with_ymd_and_hms(2025, 3, day, 12, 0, 0)
+                .unwrap()
+                .with_timezone(&Utc),
+            provider_name: "main".to_string(),
+            provider: "openai".to_string(),
+            model: model.to_string(),
+            prompt_name: prompt.to_string(),
+            api_key: None,
+            usage: TokenUsage {
+                input_tokens: input,
+                output_tokens: output,
+                estimated: false,
+            },
+        }
+    }
+
+    fn date(day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2025, 3, day)
+    }
+
+    #[test]
+    fn prices_dated_and_suffixed_models_by_longest_prefix() {
+        let prices = [
+            price("openai", "gpt-4o", 2.5, 10.0),
+            price("openai", "gpt-4o-mini", 0.15, 0.6),
+            price("anthropic", "claude-3-5-haiku", 0.8, 4.0),
+        ];
+        let cost = |model: &str| request_cost(&record(1, model, "p", 1_000_000, 0), &prices);
+        assert_eq!(cost("gpt-4o-2024-08-06"), Some(2.5));
+        assert_eq!(cost("gpt-4o-mini-2024-07-18"), Some(0.15));
+        assert_eq!(cost("gpt-4"), None);
+
+        let haiku = UsageRecord {
+            provider: "Anthropic".to_string(),
+            ..record(1, "claude-3-5-haiku-latest", "p", 0, 1_000_000)
+        };
+        assert_eq!(request_cost(&haiku, &prices), Some(4.0));
+    }
+
+    #[test]
+    fn totals_costs_and_unpriced_requests() {
+        let prices = [price("openai", "gpt-4o", 2.0, 10.0)];
+        let records = [
+            record(1, "gpt-4o", "Fix Grammar", 500_000, 50_000),
+            record(1, "gpt-4o-2024-08-06", "Summarize", 1_000_000, 0),
+            record(2, "o1", "Summarize", 10, 10),
+        ];
+        let report = build_report(&records, &prices, &UsageRange::default());
+
+        assert_eq!(report.total.requests, 3);
+        assert_eq!(report.total.input_tokens, 1_500_010);
+        assert_eq!(report.total.output_tokens, 50_010);
+        assert_eq!(report.total.unpriced_requests, 1);
+        assert!((report.total.cost - 3.5).abs() < 1e-9);
+
+        let days: Vec<&str> = report.daily.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(days, ["2025-03-01", "2025-03-02"]);
+        assert_eq!(report.monthly.len(), 1);
+        assert_eq!(report.prompts[0].name, "Summarize");
+        assert_eq!(report.prompts[0].totals.requests, 2);
+        assert_eq!(report.models.len(), 3);
+    }
+
+    #[test]
+    fn filters_by_inclusive_local_date_range() {
+        let records = [
+            record(1, "gpt-4o", "p", 1, 1),
+            record(2, "gpt-4o", "p", 1, 1),
+            record(3, "gpt-4o", "p", 1, 1),
+        ];
+        let requests = |from, to| {
+            build_report(&records, &[], &UsageRange { from, to })
+                .total
+                .requests
+        };
+        assert_eq!(requests(date(2), date(2)), 1);
+        assert_eq!(requests(date(2), None), 2);
+        assert_eq!(requests(None, date(1)), 1);
+        assert_eq!(requests(date(4), None), 0);
+    }
+}
@@ -0,0 +1,441 @@
+use crate::settings::AppState;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use tauri::{Manager, State};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Prefix of API keys that refer to a secret in the vault, e.g. `secret:openai-main`
+pub const SECRET_PREFIX: &str = "secret:";
+
+#[cfg(not(test))]
+const PBKDF2_ITERATIONS: u32 = 600_000;
+/// Few enough to keep the tests quick
+#[cfg(test)]
+const PBKDF2_ITERATIONS: u32 = 1_000;
+const SALT_LEN: usize = 16;
+
+/// Known value sealed with the vault key, used to check the passphrase on unlock
+const CHECK_NAME: &str = "vault-check";
+const CHECK_VALUE: &str = "quillbert";
+
+/// Reference stored in settings in place of the secret itself
+pub fn secret_reference(name: &str) -> String {
+    format!("{}{}", SECRET_PREFIX, name)
+}
+
+/// An encrypted value, with the nonce it was sealed with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedSecret {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// On-disk layout of the vault. Secrets are sealed with AES-256-GCM using a
+/// key derived from the passphrase, with the secret's name as associated data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VaultFile {
+    salt: String,
+    iterations: u32,
+    check: SealedSecret,
+    secrets: BTreeMap<String, SealedSecret>,
+}
+
+struct UnlockedVault {
+    key: LessSafeKey,
+    file: VaultFile,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStatus {
+    pub exists: bool,
+    pub unlocked: bool,
+    /// API keys still saved in plain text in settings.json
+    pub plaintext_keys: usize,
+}
+
+/// Secrets store encrypted with a user passphrase, unlocked once per session
+pub struct Vault {
+    path: PathBuf,
+    unlocked: RwLock<Option<UnlockedVault>>,
+    rng: SystemRandom,
+}
+
+impl Vault {
+    pub fn new(app_handle: &tauri::AppHandle) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_dir = app_handle.path().app_config_dir()?;
+        fs::create_dir_all(&config_dir)?;
+
+        Ok(Self::at(config_dir.join("secrets.vault")))
+    }
+
+    fn at(path: PathBuf) -> Self {
+        Self {
+            path,
+            unlocked: RwLock::new(None),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    pub fn status(&self, plaintext_keys: usize) -> VaultStatus {
+        VaultStatus {
+            exists: self.path.exists(),
+            unlocked: self.is_unlocked(),
+            plaintext_keys,
+        }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.unlocked.read().map(|v| v.is_some()).unwrap_or(false)
+    }
+
+    /// Unlock the vault with its passphrase, creating it on first use
+    pub async fn unlock(&self, passphrase: &str) -> Result<(), BoxError> {
+        if passphrase.is_empty() {
+            return Err("Passphrase must not be empty".into());
+        }
+
+        let unlocked = if self.path.exists() {
+            let contents = fs::read_to_string(&self.path)?;
+            let file: VaultFile = serde_json::from_str(&contents)?;
+            let salt = STANDARD.decode(&file.salt)?;
+            let key = derive_key_blocking(passphrase, salt, file.iterations).await?;
+
+            let check = open(&key, CHECK_NAME, &file.check)
+                .map_err(|_| "Incorrect passphrase for the secrets vault")?;
+            if check != CHECK_VALUE {
+                return Err("Incorrect passphrase for the secrets vault".into());
+            }
+
+            UnlockedVault { key, file }
+        } else {
+            let mut salt = [0u8; SALT_LEN];
+            self.rng
+                .fill(&mut salt)
+                .map_err(|_| "Failed to generate a salt")?;
+            let key = derive_key_blocking(passphrase, salt.to_vec(), PBKDF2_ITERATIONS).await?;
+            let check = self.seal(&key, CHECK_NAME, CHECK_VALUE)?;
+
+            let unlocked = UnlockedVault {
+                key,
+                file: VaultFile {
+                    salt: STANDARD.encode(salt),
+                    iterations: PBKDF2_ITERATIONS,
+                    check,
+                    secrets: BTreeMap::new(),
+                },
+            };
+            self.save(&unlocked.file)?;
+            unlocked
+        };
+
+        *self.unlocked.write().map_err(|e| e.to_string())? = Some(unlocked);
+        Ok(())
+    }
+
+    /// Forget the key until the vault is unlocked again
+    pub fn lock(&self) -> Result<(), BoxError> {
+        *self.unlocked.write().map_err(|e| e.to_string())? = None;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<String, BoxError> {
+        let unlocked = self.unlocked.read().map_err(|e| e.to_string())?;
+        let unlocked = unlocked
+            .as_ref()
+            .ok_or("The secrets vault is locked, unlock it in Settings first")?;
+
+        let sealed = unlocked
+            .file
+            .secrets
+            .get(name)
+            .ok_or_else(|| format!("Secret '{}' not found in the vault", name))?;
+        open(&unlocked.key, name, sealed)
+    }
+
+    /// Store a secret, returning the reference to keep in settings
+    pub fn set(&self, name: &str, value: &str) -> Result<String, BoxError> {
+        let mut unlocked = self.unlocked.write().map_err(|e| e.to_string())?;
+        let unlocked = unlocked
+            .as_mut()
+            .ok_or("The secrets vault is locked, unlock it in Settings first")?;
+
+        let sealed = self.seal(&unlocked.key, name, value)?;
+        unlocked.file.secrets.insert(name.to_string(), sealed);
+        self.save(&unlocked.file)?;
+
+        Ok(secret_reference(name))
+    }
+
+    /// Delete secrets, e.g. the ones of a deleted config. Sealed secrets are
+    /// dropped without the key, so this also works while the vault is locked.
+    pub fn remove(&self, names: &[String]) -> Result<(), BoxError> {
+        if names.is_empty() || !self.path.exists() {
+            return Ok(());
+        }
+
+        let mut unlocked = self.unlocked.write().map_err(|e| e.to_string())?;
+        match unlocked.as_mut() {
+            Some(unlocked) => {
+                unlocked
+                    .file
+                    .secrets
+                    .retain(|name, _| !names.contains(name));
+                self.save(&unlocked.file)
+            }
+            None => {
+                let contents = fs::read_to_string(&self.path)?;
+                let mut file: VaultFile = serde_json::from_str(&contents)?;
+                file.secrets.retain(|name, _| !names.contains(name));
+                self.save(&file)
+            }
+        }
+    }
+
+    fn seal(&self, key: &LessSafeKey, name: &str, value: &str) -> Result<SealedSecret, BoxError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| "Failed to generate a nonce")?;
+
+        let mut in_out = value.as_bytes().to_vec();
+        key.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(name.as_bytes()),
+            &mut in_out,
+        )
+        .map_err(|_| format!("Failed to encrypt secret '{}'", name))?;
+
+        Ok(SealedSecret {
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(in_out),
+        })
+    }
+
+    /// Write the vault readable by its owner only
+    fn save(&self, file: &VaultFile) -> Result<(), BoxError> {
+        let contents = serde_json::to_string_pretty(file)?;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            // The mode only applies to new files, so also fix up older vaults
+            if self.path.exists() {
+                fs::set_permissions(&self.path, fs::Permissions::from_mode(0o600))?;
+            }
+        }
+
+        let mut out = options.open(&self.path)?;
+        out.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey, BoxError> {
+    let iterations = NonZeroU32::new(iterations).ok_or("Invalid vault iteration count")?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| "Failed to derive vault key")?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Derive the key on a blocking thread, as the many PBKDF2 iterations take a
+/// noticeable moment
+async fn derive_key_blocking(
+    passphrase: &str,
+    salt: Vec<u8>,
+    iterations: u32,
+) -> Result<LessSafeKey, BoxError> {
+    let passphrase = passphrase.to_string();
+    tokio::task::spawn_blocking(move || derive_key(&passphrase, &salt, iterations)).await?
+}
+
+fn open(key: &LessSafeKey, name: &str, sealed: &SealedSecret) -> Result<String, BoxError> {
+    let nonce: [u8; NONCE_LEN] = STANDARD
+        .decode(&sealed.nonce)?
+        .try_into()
+        .map_err(|_| format!("Secret '{}' has an invalid nonce", name))?;
+    let mut in_out = STANDARD.decode(&sealed.ciphertext)?;
+
+    let plaintext = key
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(name.as_bytes()),
+            &mut in_out,
+        )
+        .map_err(|_| format!("Failed to decrypt secret '{}'", name))?;
+
+    Ok(String::from_utf8(plaintext.to_vec())?)
+}
+
+#[tauri::command]
+pub async fn get_vault_status(state: State<'_, AppState>) -> Result<VaultStatus, String> {
+    let plaintext_keys = state.plaintext_key_count().map_err(|e| e.to_string())?;
+    Ok(state.vault.status(plaintext_keys))
+}
+
+/// Unlock the vault, creating it with this passphrase if it doesn't exist yet.
+/// Any plaintext API keys left in settings are moved into the vault.
+#[tauri::command]
+pub async fn unlock_vault(state: State<'_, AppState>, passphrase: String) -> Result<(), String> {
+    state
+        .vault
+        .unlock(&passphrase)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let migrated = state
+        .migrate_plaintext_keys()
+        .await
+        .map_err(|e| e.to_string())?;
+    if migrated > 0 {
+        println!(
+            "Moved {} plaintext API keys into the secrets vault",
+            migrated
+        );
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn lock_vault(state: State<'_, AppState>) -> Result<(), String> {
+    state.lock_vault().await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A vault in a fresh temporary directory
+    fn vault(test: &str) -> Vault {
+        let dir =
+            std::env::temp_dir().join(format!("quillbert-vault-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Vault::at(dir.join("secrets.vault"))
+    }
+
+    fn unlock(vault: &Vault, passphrase: &str) -> Result<(), BoxError> {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(vault.unlock(passphrase))
+    }
+
+    fn read_file(vault: &Vault) -> VaultFile {
+        serde_json::from_str(&fs::read_to_string(&vault.path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn secrets_survive_locking_and_unlocking() {
+        let vault = vault("round-trip");
+        unlock(&vault, "correct horse").unwrap();
+        assert_eq!(vault.set("openai", "sk-123").unwrap(), "secret:openai");
+
+        vault.lock().unwrap();
+        let reopened = Vault::at(vault.path.clone());
+        unlock(&reopened, "correct horse").unwrap();
+        assert_eq!(reopened.get("openai").unwrap(), "sk-123");
+
+        let sealed = fs::read_to_string(&vault.path).unwrap();
+        assert!(!sealed.contains("sk-123"));
+    }
+
+    #[test]
+    fn wrong_passphrase_is_an_error() {
+        let vault = vault("wrong-passphrase");
+        unlock(&vault, "correct horse").unwrap();
+        vault.set("openai", "sk-123").unwrap();
+        vault.lock().unwrap();
+
+        let err = unlock(&vault, "battery staple").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Incorrect passphrase for the secrets vault"
+        );
+        assert!(!vault.is_unlocked());
+        assert!(vault.get("openai").is_err());
+    }
+
+    #[test]
+    fn get_fails_while_locked() {
+        let vault = vault("locked");
+        assert!(vault
+            .get("openai")
+            .unwrap_err()
+            .to_string()
+            .contains("locked"));
+
+        unlock(&vault, "correct horse").unwrap();
+        vault.set("openai", "sk-123").unwrap();
+        vault.lock().unwrap();
+        assert!(vault
+            .get("openai")
+            .unwrap_err()
+            .to_string()
+            .contains("locked"));
+        assert!(vault.set("openai", "sk-456").is_err());
+    }
+
+    #[test]
+    fn every_set_uses_a_new_nonce() {
+        let vault = vault("nonce");
+        unlock(&vault, "correct horse").unwrap();
+
+        vault.set("openai", "sk-123").unwrap();
+        let first = read_file(&vault).secrets["openai"].clone();
+        vault.set("openai", "sk-123").unwrap();
+        let second = read_file(&vault).secrets["openai"].clone();
+
+        assert_ne!(first.nonce, second.nonce);
+        assert_ne!(first.ciphertext, second.ciphertext);
+        assert_ne!(first.nonce, read_file(&vault).check.nonce);
+    }
+
+    #[test]
+    fn removes_secrets_while_locked() {
+        let vault = vault("remove");
+        unlock(&vault, "correct horse").unwrap();
+        vault.set("openai", "sk-123").unwrap();
+        vault.set("groq", "gsk-456").unwrap();
+        vault.lock().unwrap();
+
+        vault.remove(&["openai".to_string()]).unwrap();
+        unlock(&vault, "correct horse").unwrap();
+        assert!(vault.get("openai").is_err());
+        assert_eq!(vault.get("groq").unwrap(), "gsk-456");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn vault_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let vault = vault("permissions");
+        unlock(&vault, "correct horse").unwrap();
+        let mode = fs::metadata(&vault.path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
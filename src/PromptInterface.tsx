@@ -1,7 +1,17 @@
 import React, { useState, useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import { CustomPrompt, PromptResponse } from "./types";
+import {
+  CustomPrompt,
+  PromptFallback,
+  PromptJob,
+  PromptPreflight,
+  PromptProgress,
+  PromptQueued,
+  PromptResponse,
+  PromptResponseChunk,
+  PromptRetry,
+} from "./types";
 import { useStatusNotification } from "@/hooks/useStatusNotification";
 import { useShortcutListener } from "@/hooks/useShortcutListener";
 import NotificationHeader from "@/components/NotificationHeader";
@@ -16,10 +26,12 @@ const PromptInterface: React.FC = () => {
   const [error, setError] = useState<string | null>(null);
   const [shortcuts, setShortcuts] = useState<any[]>([]);
   const [processingPrompt, setProcessingPrompt] = useState<string | null>(null);
+  const [currentJobId, setCurrentJobId] = useState<number | null>(null);
   const [selectedText, setSelectedText] = useState<string | null>(null);
   const [promptResponse, setPromptResponse] = useState<PromptResponse | null>(
     null,
   );
+  const [preflight, setPreflight] = useState<PromptPreflight | null>(null);
 
   const {
     status,
@@ -49,12 +61,13 @@ const PromptInterface: React.FC = () => {
 
   // Execute a custom prompt based on name
   const executeCustomPrompt = useCallback(
-    async (promptName: string): Promise<void> => {
+    async (promptName: string, confirmed = false): Promise<void> => {
       try {
         console.log(`Executing custom prompt: ${promptName}`);
 
         // Clear previous response when starting a new prompt
         setPromptResponse(null);
+        setPreflight(null);
 
         // Set processing state and show loading status
         setProcessingPrompt(promptName);
@@ -62,7 +75,7 @@ const PromptInterface: React.FC = () => {
 
         // We don't immediately clear processing state since it will be cleared
         // when we receive the prompt-response event
-        await invoke("execute_custom_prompt", { promptName });
+        await invoke("execute_custom_prompt", { promptName, confirmed });
 
         // Note: we no longer show success status here - that's handled by the event listener
       } catch (err) {
@@ -78,11 +91,45 @@ const PromptInterface: React.FC = () => {
 
         // Clear processing state on error
         setProcessingPrompt(null);
+        setCurrentJobId(null);
       }
     },
     [showStatus],
   );
 
+  // Refine the last response by continuing its conversation
+  const sendFollowUp = useCallback(
+    async (message: string, confirmed = false): Promise<void> => {
+      const jobId = promptResponse?.job_id;
+      if (jobId === undefined) return;
+      const promptName = promptResponse!.prompt_name;
+      try {
+        setPreflight(null);
+        setProcessingPrompt(promptName);
+        showStatus("loading", promptName, `Following up on "${promptName}"...`);
+        await invoke("follow_up", { jobId, message, confirmed });
+      } catch (err) {
+        console.error(`Error sending follow-up for job ${jobId}:`, err);
+        const errorMessage =
+          typeof err === "string" ? err : "Failed to send follow-up";
+        showStatus("error", promptName, errorMessage);
+        setProcessingPrompt(null);
+        setCurrentJobId(null);
+      }
+    },
+    [promptResponse, showStatus],
+  );
+
+  // Cancel the prompt that is currently running
+  const cancelCurrentPrompt = useCallback(async (): Promise<void> => {
+    if (currentJobId === null) return;
+    try {
+      await invoke("cancel_prompt", { jobId: currentJobId });
+    } catch (err) {
+      console.error(`Error cancelling prompt job ${currentJobId}:`, err);
+    }
+  }, [currentJobId]);
+
   // Set up shortcut listener
   useShortcutListener({
     shortcuts,
@@ -155,6 +202,7 @@ const PromptInterface: React.FC = () => {
 
       // Clear processing state and update status to success
       setProcessingPrompt(null);
+      setCurrentJobId(null);
       showStatus(
         "success",
         response.prompt_name,
@@ -163,6 +211,94 @@ const PromptInterface: React.FC = () => {
     });
     unlistenPromises.push(promptResponsePromise);
 
+    // Listen for streamed chunks and append them to the response as they arrive
+    const promptChunkPromise = listen("prompt-response-chunk", (event) => {
+      const chunk = event.payload as PromptResponseChunk;
+      setPromptResponse((prev) => ({
+        job_id: chunk.job_id,
+        prompt_name: chunk.prompt_name,
+        response:
+          chunk.sequence === 0 || !prev
+            ? chunk.chunk
+            : prev.response + chunk.chunk,
+      }));
+    });
+    unlistenPromises.push(promptChunkPromise);
+
+    // Track the job id of the running prompt so it can be cancelled
+    const promptStartedPromise = listen("prompt-started", (event) => {
+      const job = event.payload as PromptJob;
+      setCurrentJobId(job.job_id);
+    });
+    unlistenPromises.push(promptStartedPromise);
+
+    // Report retries of transient provider failures
+    const promptRetryPromise = listen("prompt-retry", (event) => {
+      const retry = event.payload as PromptRetry;
+      showStatus(
+        "loading",
+        retry.prompt_name,
+        `Retrying "${retry.prompt_name}" in ${Math.ceil(retry.delay_ms / 1000)}s (${retry.attempt}/${retry.max_retries})`,
+      );
+    });
+    unlistenPromises.push(promptRetryPromise);
+
+    // A provider failed before streaming anything and the next one is being tried
+    const promptFallbackPromise = listen("prompt-fallback", (event) => {
+      const fallback = event.payload as PromptFallback;
+      setPromptResponse(null);
+      showStatus(
+        "loading",
+        fallback.prompt_name,
+        `"${fallback.failed_provider}" failed, trying "${fallback.next_provider}"`,
+      );
+    });
+    unlistenPromises.push(promptFallbackPromise);
+
+    // The prompt would overflow the model's context window, so ask before sending it
+    const promptPreflightPromise = listen("prompt-preflight", (event) => {
+      const check = event.payload as PromptPreflight;
+      setProcessingPrompt(null);
+      setPreflight(check);
+      showStatus("error", check.prompt_name, `"${check.prompt_name}" is too long`);
+    });
+    unlistenPromises.push(promptPreflightPromise);
+
+    // Long selections are processed chunk by chunk
+    const promptProgressPromise = listen("prompt-progress", (event) => {
+      const progress = event.payload as PromptProgress;
+      showStatus(
+        "loading",
+        progress.prompt_name,
+        progress.stage === "map"
+          ? `Processing "${progress.prompt_name}", chunk ${progress.completed}/${progress.total} done`
+          : `Combining the results of "${progress.prompt_name}"`,
+      );
+    });
+    unlistenPromises.push(promptProgressPromise);
+
+    // The provider's client-side limits hold the request back for a while
+    const promptQueuedPromise = listen("prompt-queued", (event) => {
+      const queued = event.payload as PromptQueued;
+      showStatus(
+        "loading",
+        queued.prompt_name,
+        queued.wait_ms != null
+          ? `"${queued.prompt_name}" queued for "${queued.provider_name}", about ${Math.ceil(queued.wait_ms / 1000)}s`
+          : `"${queued.prompt_name}" queued until a "${queued.provider_name}" request finishes`,
+      );
+    });
+    unlistenPromises.push(promptQueuedPromise);
+
+    // Listen for cancelled prompts
+    const promptCancelledPromise = listen("prompt-cancelled", (event) => {
+      const job = event.payload as PromptJob;
+      setProcessingPrompt(null);
+      setCurrentJobId(null);
+      showStatus("error", job.prompt_name, `Cancelled "${job.prompt_name}"`);
+    });
+    unlistenPromises.push(promptCancelledPromise);
+
     // Cleanup listener and timeouts on component unmount
     return () => {
       unlistenPromises.forEach((promise) => {
@@ -181,6 +317,7 @@ const PromptInterface: React.FC = () => {
         loading={loading}
         status={status}
         onRefresh={handleRefresh}
+        onCancel={currentJobId !== null ? cancelCurrentPrompt : undefined}
       />
 
       {error && (
@@ -201,8 +338,36 @@ const PromptInterface: React.FC = () => {
         executeCustomPrompt={executeCustomPrompt}
       />
 
+      {preflight && (
+        <div className="mx-4 mb-2 p-2 text-xs bg-amber-50 border border-amber-200 rounded-md text-amber-800">
+          <p className="mb-2">{preflight.message}.</p>
+          <div className="flex gap-2">
+            <button
+              onClick={() =>
+                preflight.follow_up !== undefined
+                  ? sendFollowUp(preflight.follow_up, true)
+                  : executeCustomPrompt(preflight.prompt_name, true)
+              }
+              className="px-2 py-0.5 rounded bg-amber-600 text-white hover:bg-amber-700"
+            >
+              Send anyway
+            </button>
+            <button
+              onClick={() => setPreflight(null)}
+              className="px-2 py-0.5 rounded text-amber-800 hover:underline"
+            >
+              Cancel
+            </button>
+          </div>
+        </div>
+      )}
+
       <SelectedTextPanel selectedText={selectedText} />
-      <ResponsePanel response={promptResponse} />
+      <ResponsePanel
+        response={promptResponse}
+        onFollowUp={sendFollowUp}
+        followUpDisabled={processingPrompt !== null}
+      />
     </div>
   );
 };
@@ -8,15 +8,17 @@ import {
   MessageSquare,
   Settings as SettingsIcon,
   RefreshCw,
+  BarChart3,
 } from "lucide-react";
 import SystemShortcuts from "@/components/settings/SystemShortcuts";
 import LLMConfigurations from "@/components/settings/LLMConfigurations";
 import CustomPrompts from "@/components/settings/CustomPrompts";
+import Usage from "@/components/settings/Usage";
 import { ShortcutConfig, LLMConfig, CustomPromptConfig } from "@/types";
 import Button from "@/components/ui/Button";
 import "./index.css";
 
-type TabType = "shortcuts" | "llm" | "prompts";
+type TabType = "shortcuts" | "llm" | "prompts" | "usage";
 
 const Settings: React.FC = () => {
   // Tab state
@@ -61,9 +63,15 @@ const Settings: React.FC = () => {
       await loadData();
     });
 
+    // Providers found on first launch are added in the background
+    const discoveredPromise = listen("providers-discovered", async () => {
+      await loadData();
+    });
+
     // Cleanup listener on component unmount
     return () => {
       unlistenPromise.then((unlistenFn) => unlistenFn());
+      discoveredPromise.then((unlistenFn) => unlistenFn());
     };
   }, [loadData]);
 
@@ -136,6 +144,8 @@ const Settings: React.FC = () => {
             onUpdate={loadData}
           />
         );
+      case "usage":
+        return <Usage />;
       default:
         return null;
     }
@@ -166,6 +176,11 @@ const Settings: React.FC = () => {
             label="Custom Prompts"
             icon={<MessageSquare className="w-3.5 h-3.5" />}
           />
+          <TabButton
+            id="usage"
+            label="Usage"
+            icon={<BarChart3 className="w-3.5 h-3.5" />}
+          />
         </div>
       </div>
 
@@ -1,5 +1,5 @@
 import React from "react";
-import { RefreshCw, Settings } from "lucide-react";
+import { RefreshCw, Settings, X } from "lucide-react";
 import StatusIndicator from "./StatusIndicator";
 import { NotificationStatus } from "@/types";
 import { invoke } from "@tauri-apps/api/core";
@@ -8,12 +8,14 @@ interface NotificationHeaderProps {
   loading: boolean;
   status: NotificationStatus;
   onRefresh: () => void;
+  onCancel?: () => void;
 }
 
 const NotificationHeader: React.FC<NotificationHeaderProps> = ({
   loading,
   status,
   onRefresh,
+  onCancel,
 }) => {
   const openSettings = async () => {
     try {
@@ -38,7 +40,17 @@ const NotificationHeader: React.FC<NotificationHeaderProps> = ({
 
       {/* Inline status indicator with fixed height/width to prevent layout shift */}
       <StatusIndicator status={status} />
-      
+
+      {onCancel && (
+        <button
+          onClick={onCancel}
+          className="ml-3 text-gray-400 hover:text-red-500 transition-colors"
+          aria-label="Cancel running prompt"
+        >
+          <X className="h-4 w-4" />
+        </button>
+      )}
+
       <button
         onClick={openSettings}
         className="ml-3 text-gray-400 hover:text-gray-600 transition-colors"
@@ -3,10 +3,24 @@ import { PromptResponse } from "@/types";
 
 interface ResponsePanelProps {
   response: PromptResponse | null;
+  onFollowUp?: (message: string) => void;
+  followUpDisabled?: boolean;
 }
 
-const ResponsePanel: React.FC<ResponsePanelProps> = ({ response }) => {
+const ResponsePanel: React.FC<ResponsePanelProps> = ({
+  response,
+  onFollowUp,
+  followUpDisabled,
+}) => {
   const [isExpanded, setIsExpanded] = useState(true);
+  const [followUp, setFollowUp] = useState("");
+
+  const submitFollowUp = (e: React.FormEvent) => {
+    e.preventDefault();
+    if (!onFollowUp || followUpDisabled || followUp.trim() === "") return;
+    onFollowUp(followUp.trim());
+    setFollowUp("");
+  };
 
   return (
     <div className="border-t border-gray-200 p-4">
@@ -16,6 +30,9 @@ const ResponsePanel: React.FC<ResponsePanelProps> = ({ response }) => {
           <span className="text-blue-600">
             {response ? response.prompt_name : "None"}
           </span>
+          {response?.cached && (
+            <span className="ml-1 text-gray-400">(cached)</span>
+          )}
         </h2>
         {response && (
           <button
@@ -44,6 +61,19 @@ const ResponsePanel: React.FC<ResponsePanelProps> = ({ response }) => {
           </div>
         )}
       </div>
+
+      {onFollowUp && response?.job_id !== undefined && (
+        <form onSubmit={submitFollowUp} className="mt-2">
+          <input
+            type="text"
+            value={followUp}
+            onChange={(e) => setFollowUp(e.target.value)}
+            disabled={followUpDisabled}
+            placeholder='Follow up, e.g. "shorter" or "as bullet points"'
+            className="w-full px-2 py-1 text-xs rounded border border-gray-300 bg-white text-gray-700 focus:border-blue-500 focus:outline-none focus:ring-1 focus:ring-blue-500 disabled:bg-gray-50"
+          />
+        </form>
+      )}
     </div>
   );
 };
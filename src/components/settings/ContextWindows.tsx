@@ -0,0 +1,130 @@
+import React, { useState, useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Plus, Save, Trash2 } from "lucide-react";
+import { ContextWindow } from "@/types";
+import Input from "@/components/ui/Input";
+import Button from "@/components/ui/Button";
+import Card from "@/components/ui/Card";
+
+// Edits the table used to refuse prompts that won't fit a model's context window
+const ContextWindows: React.FC = () => {
+  const [windows, setWindows] = useState<ContextWindow[]>([]);
+  const [error, setError] = useState<string>("");
+  const [saving, setSaving] = useState<boolean>(false);
+
+  useEffect(() => {
+    invoke<ContextWindow[]>("get_context_windows")
+      .then(setWindows)
+      .catch((err) => console.error("Error loading context windows:", err));
+  }, []);
+
+  const updateWindow = (
+    index: number,
+    field: keyof ContextWindow,
+    value: string,
+  ) => {
+    setWindows((prev) =>
+      prev.map((window, idx) =>
+        idx === index
+          ? { ...window, [field]: field === "tokens" ? Number(value) : value }
+          : window,
+      ),
+    );
+  };
+
+  const handleSave = async () => {
+    setSaving(true);
+    try {
+      await invoke("update_context_windows", {
+        windows: windows.filter((w) => w.provider.trim() && w.model.trim()),
+      });
+      setError("");
+    } catch (err) {
+      setError(
+        typeof err === "string" ? err : "Failed to save context windows",
+      );
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  return (
+    <Card title="Context Windows (tokens)" className="mt-6">
+      <p className="text-xs text-gray-500 mb-3">
+        Prompts that would not fit are held back for confirmation. Models match
+        by prefix, so "claude-" covers every Claude model.
+      </p>
+      {error && (
+        <div className="bg-red-50 text-red-500 p-3 rounded text-xs border border-red-200 mb-3">
+          {error}
+        </div>
+      )}
+      <div className="space-y-2">
+        {windows.map((window, index) => (
+          <div key={index} className="grid grid-cols-9 gap-2 items-center">
+            <div className="col-span-2">
+              <Input
+                value={window.provider}
+                onChange={(e) =>
+                  updateWindow(index, "provider", e.target.value)
+                }
+                placeholder="Provider"
+              />
+            </div>
+            <div className="col-span-4">
+              <Input
+                value={window.model}
+                onChange={(e) => updateWindow(index, "model", e.target.value)}
+                placeholder="Model"
+              />
+            </div>
+            <div className="col-span-2">
+              <Input
+                type="number"
+                min="1"
+                value={window.tokens}
+                onChange={(e) => updateWindow(index, "tokens", e.target.value)}
+                placeholder="Tokens"
+              />
+            </div>
+            <button
+              onClick={() =>
+                setWindows((prev) => prev.filter((_, idx) => idx !== index))
+              }
+              className="text-gray-400 hover:text-red-500"
+              title="Remove context window"
+            >
+              <Trash2 className="h-4 w-4" />
+            </button>
+          </div>
+        ))}
+
+        <div className="flex gap-2">
+          <Button
+            onClick={() =>
+              setWindows((prev) => [
+                ...prev,
+                { provider: "", model: "", tokens: 8192 },
+              ])
+            }
+            variant="outline"
+            size="sm"
+            leftIcon={<Plus className="h-3.5 w-3.5" />}
+          >
+            Add Model
+          </Button>
+          <Button
+            onClick={handleSave}
+            isLoading={saving}
+            size="sm"
+            leftIcon={<Save className="h-3.5 w-3.5" />}
+          >
+            Save Context Windows
+          </Button>
+        </div>
+      </div>
+    </Card>
+  );
+};
+
+export default ContextWindows;
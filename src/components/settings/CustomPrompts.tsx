@@ -1,6 +1,6 @@
 import React, { useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { Edit2, Plus, Trash2, Save, X } from "lucide-react";
+import { Edit2, Plus, Trash2, Save, X, Eraser } from "lucide-react";
 import { CustomPromptConfig, LLMConfig } from "@/types";
 import { ShortcutItem } from "@/components/ShortcutItem";
 import { useShortcutEditor } from "@/hooks/useShortcutEditor";
@@ -21,6 +21,7 @@ interface CustomPromptsProps {
 const defaultPrompt: CustomPromptConfig = {
   name: "",
   provider_name: "",
+  fallback_providers: [],
   prompt_template: "",
   shortcut: "",
 };
@@ -45,10 +46,43 @@ const CustomPrompts: React.FC<CustomPromptsProps> = ({
     const { name, value } = e.target;
     setCurrentPrompt((prev) => ({
       ...prev,
-      [name]: value,
+      [name]:
+        name === "cache_ttl_secs"
+          ? value === ""
+            ? null
+            : parseInt(value, 10)
+          : value,
     }));
   };
 
+  const handleChunkingChange = (
+    e: React.ChangeEvent<HTMLInputElement | HTMLTextAreaElement>,
+  ) => {
+    const { name, value } = e.target;
+    setCurrentPrompt((prev) => {
+      const chunking = { chunk_tokens: 0, ...prev.chunking };
+      if (name === "chunk_tokens") {
+        // Clearing the budget turns chunking off
+        if (value === "") return { ...prev, chunking: null };
+        chunking.chunk_tokens = parseInt(value, 10);
+      } else {
+        chunking.reduce_prompt = value;
+      }
+      return { ...prev, chunking };
+    });
+  };
+
+  const handleClearCache = async () => {
+    try {
+      await invoke("clear_response_cache");
+      setError("");
+    } catch (err) {
+      setError(
+        typeof err === "string" ? err : "Failed to clear the response cache",
+      );
+    }
+  };
+
   const handleEditPrompt = (index: number) => {
     setCurrentPrompt(prompts[index]);
     setIsEditing(true);
@@ -86,6 +120,8 @@ const CustomPrompts: React.FC<CustomPromptsProps> = ({
       )
     )
       return "Prompt name must be unique";
+    if (currentPrompt.chunking && !(currentPrompt.chunking.chunk_tokens >= 1))
+      return "Chunk size must be at least 1 token";
     return null;
   };
 
@@ -100,7 +136,24 @@ const CustomPrompts: React.FC<CustomPromptsProps> = ({
     setError("");
 
     try {
-      await invoke("register_custom_prompt", { config: currentPrompt });
+      const config = {
+        ...currentPrompt,
+        fallback_providers: (currentPrompt.fallback_providers ?? []).filter(
+          (name) => name !== "",
+        ),
+        chunking: currentPrompt.chunking
+          ? {
+              ...currentPrompt.chunking,
+              reduce_prompt: currentPrompt.chunking.reduce_prompt?.trim()
+                ? currentPrompt.chunking.reduce_prompt
+                : null,
+            }
+          : null,
+        system_prompt: currentPrompt.system_prompt?.trim()
+          ? currentPrompt.system_prompt
+          : null,
+      };
+      await invoke("register_custom_prompt", { config });
 
       // Reset form
       setCurrentPrompt(defaultPrompt);
@@ -128,16 +181,26 @@ const CustomPrompts: React.FC<CustomPromptsProps> = ({
     <div className="p-6">
       <div className="flex items-center justify-between mb-4">
         <h2 className="text-lg font-medium text-gray-800">Custom Prompts</h2>
-        {isEditing && (
+        <div className="flex items-center gap-2">
           <Button
-            onClick={handleCancelEdit}
+            onClick={handleClearCache}
             variant="ghost"
             size="sm"
-            leftIcon={<X className="w-3.5 h-3.5" />}
+            leftIcon={<Eraser className="w-3.5 h-3.5" />}
           >
-            Cancel Editing
+            Clear Response Cache
           </Button>
-        )}
+          {isEditing && (
+            <Button
+              onClick={handleCancelEdit}
+              variant="ghost"
+              size="sm"
+              leftIcon={<X className="w-3.5 h-3.5" />}
+            >
+              Cancel Editing
+            </Button>
+          )}
+        </div>
       </div>
 
       <p className="text-sm text-gray-600 mb-6">
@@ -179,6 +242,23 @@ const CustomPrompts: React.FC<CustomPromptsProps> = ({
             </Select>
           </div>
 
+          <Input
+            label="Fallback Providers"
+            type="text"
+            name="fallback_providers"
+            value={(currentPrompt.fallback_providers ?? []).join(", ")}
+            onChange={(e) =>
+              setCurrentPrompt((prev) => ({
+                ...prev,
+                fallback_providers: e.target.value
+                  .split(",")
+                  .map((name) => name.trim()),
+              }))
+            }
+            placeholder="e.g., local-ollama, groq"
+            helperText="Configurations or provider groups to try in order if the provider fails"
+          />
+
           <div>
             <label className="block text-xs font-medium text-gray-600 mb-1">
               Shortcut
@@ -200,6 +280,16 @@ const CustomPrompts: React.FC<CustomPromptsProps> = ({
             </p>
           </div>
 
+          <Textarea
+            label="System Prompt"
+            name="system_prompt"
+            value={currentPrompt.system_prompt ?? ""}
+            onChange={handleInputChange}
+            rows={3}
+            placeholder="Optional instructions for the model..."
+            helperText="Added after the provider's system prompt, if it has one"
+          />
+
           <Textarea
             label="Prompt Template"
             name="prompt_template"
@@ -210,6 +300,40 @@ const CustomPrompts: React.FC<CustomPromptsProps> = ({
             helperText="Use {{selectedText}} as a placeholder for the selected text"
           />
 
+          <Input
+            label="Cache Responses (seconds)"
+            type="number"
+            name="cache_ttl_secs"
+            min={0}
+            value={currentPrompt.cache_ttl_secs ?? ""}
+            onChange={handleInputChange}
+            placeholder="Not cached"
+            helperText="Re-running this prompt on the same text within this time reuses the earlier response"
+          />
+
+          <Input
+            label="Chunk Size (tokens)"
+            type="number"
+            name="chunk_tokens"
+            min={1}
+            value={currentPrompt.chunking?.chunk_tokens ?? ""}
+            onChange={handleChunkingChange}
+            placeholder="Not split"
+            helperText="Longer selections are split on paragraphs and the template runs on each chunk"
+          />
+
+          {currentPrompt.chunking && (
+            <Textarea
+              label="Combine Template"
+              name="reduce_prompt"
+              value={currentPrompt.chunking.reduce_prompt ?? ""}
+              onChange={handleChunkingChange}
+              rows={3}
+              placeholder="e.g. Merge these partial summaries into one: {{selectedText}}"
+              helperText="Optional. {{selectedText}} is replaced by the results of all chunks; without a template they are joined"
+            />
+          )}
+
           <Button
             onClick={handleSavePrompt}
             isLoading={loading}
@@ -1,12 +1,25 @@
 import React, { useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { Edit2, Plus, Trash2, X } from "lucide-react";
-import { LLMConfig } from "@/types";
+import { Edit2, Plus, Search, Trash2, X } from "lucide-react";
+import {
+  CommandConfig,
+  ConfigTestResult,
+  CustomHttpConfig,
+  DiscoveryReport,
+  KeySource,
+  LLMConfig,
+  LLMProvider,
+  ModelInfo,
+  RegisterLlmResult,
+} from "@/types";
 import Input from "@/components/ui/Input";
 import Select from "@/components/ui/Select";
+import Textarea from "@/components/ui/Textarea";
 import Button from "@/components/ui/Button";
 import Card from "@/components/ui/Card";
 import { ConfigCard } from "@/components/ui/Card";
+import VaultPanel from "@/components/settings/VaultPanel";
+import ContextWindows from "@/components/settings/ContextWindows";
 
 interface LLMConfigurationsProps {
   configs: LLMConfig[];
@@ -20,8 +33,64 @@ const defaultConfig: LLMConfig = {
   model: "",
   temperature: 0.7,
   max_tokens: 1000,
+  stream: false,
 };
 
+const keySourceLabels: Record<KeySource, string> = {
+  none: "No key",
+  plaintext: "Plain text key",
+  vault: "Key in vault",
+  env: "Key from env",
+  file: "Key from file",
+};
+
+// Providers that run locally and need no API key
+const keylessProviders: LLMProvider[] = ["mock", "custom_http", "command"];
+
+const emptyCommand: CommandConfig = {
+  program: "",
+  args: [],
+  prompt_input: "stdin",
+};
+
+const emptyCustomHttp: CustomHttpConfig = {
+  url: "",
+  headers: {},
+  body_template: '{"model": "{{model}}", "prompt": "{{prompt}}"}',
+  response_pointer: "",
+};
+
+// Headers are edited as one "Name: value" pair per line
+const formatHeaders = (headers: Record<string, string> = {}): string =>
+  Object.entries(headers)
+    .map(([name, value]) => `${name}: ${value}`)
+    .join("\n");
+
+const parseHeaders = (text: string): Record<string, string> =>
+  Object.fromEntries(
+    text
+      .split("\n")
+      .map((line) => line.split(/:(.*)/s).map((part) => part.trim()))
+      .filter(([name]) => name !== "")
+      .map(([name, value]) => [name, value ?? ""]),
+  );
+
+// Blank entries in the stop sequences, key pool, no-proxy hosts and
+// arguments fields are not meant as empty values
+const withoutBlankLines = (config: LLMConfig): LLMConfig => ({
+  ...config,
+  stop: (config.stop ?? []).filter((stop) => stop !== ""),
+  api_keys: (config.api_keys ?? []).filter((key) => key.trim() !== ""),
+  no_proxy: (config.no_proxy ?? []).filter((host) => host !== ""),
+  command: config.command && {
+    ...config.command,
+    args: (config.command.args ?? []).filter((arg) => arg.trim() !== ""),
+    env_passthrough: config.command.env_passthrough?.filter(
+      (name) => name !== "",
+    ),
+  },
+});
+
 const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
   configs,
   onUpdate,
@@ -30,16 +99,119 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
   const [editingIndex, setEditingIndex] = useState<number | null>(null);
   const [error, setError] = useState<string>("");
   const [loading, setLoading] = useState<boolean>(false);
+  const [warnings, setWarnings] = useState<string[]>([]);
+  const [models, setModels] = useState<ModelInfo[]>([]);
+  const [loadingModels, setLoadingModels] = useState<boolean>(false);
+  const [testResult, setTestResult] = useState<ConfigTestResult | null>(null);
+  const [testing, setTesting] = useState<boolean>(false);
+  const [requireTest, setRequireTest] = useState<boolean>(true);
+  const [headersText, setHeadersText] = useState<string>("");
+  const [extraHeadersText, setExtraHeadersText] = useState<string>("");
+  const [discovering, setDiscovering] = useState<boolean>(false);
+  const [discoveryReport, setDiscoveryReport] =
+    useState<DiscoveryReport | null>(null);
 
   const handleConfigChange = (
-    e: React.ChangeEvent<HTMLInputElement | HTMLSelectElement>,
+    e: React.ChangeEvent<
+      HTMLInputElement | HTMLTextAreaElement | HTMLSelectElement
+    >,
   ) => {
     const { name, value } = e.target;
     const numberFields = ["temperature", "max_tokens"];
+    const optionalNumberFields = [
+      "timeout_secs",
+      "max_retries",
+      "key_cooldown_secs",
+      "requests_per_minute",
+      "tokens_per_minute",
+      "max_concurrent",
+      "top_p",
+      "top_k",
+      "seed",
+      "thinking_budget",
+    ];
+    const optionalTextFields = [
+      "base_url",
+      "proxy_url",
+      "system_prompt",
+      "reasoning_effort",
+    ];
+    const booleanFields = ["stream"];
 
     setCurrentConfig((prev) => ({
       ...prev,
-      [name]: numberFields.includes(name) ? Number(value) : value,
+      [name]: numberFields.includes(name)
+        ? Number(value)
+        : optionalNumberFields.includes(name)
+          ? value === ""
+            ? null
+            : Number(value)
+          : booleanFields.includes(name)
+            ? value === "true"
+            : optionalTextFields.includes(name) && value.trim() === ""
+              ? null
+              : value,
+    }));
+  };
+
+  const handleMockChange = (e: React.ChangeEvent<HTMLInputElement>) => {
+    const { name, value } = e.target;
+    setCurrentConfig((prev) => ({
+      ...prev,
+      mock: {
+        ...prev.mock,
+        [name]:
+          value.trim() === ""
+            ? null
+            : name === "fixtures_path"
+              ? value
+              : Number(value),
+      },
+    }));
+  };
+
+  // Headers are kept as text while editing and parsed when the config is sent
+  const withHeaders = (config: LLMConfig): LLMConfig => ({
+    ...config,
+    headers: parseHeaders(extraHeadersText),
+    custom_http: config.custom_http && {
+      ...config.custom_http,
+      headers: parseHeaders(headersText),
+    },
+  });
+
+  const handleCustomHttpChange = (
+    e: React.ChangeEvent<HTMLInputElement | HTMLTextAreaElement>,
+  ) => {
+    const { name, value } = e.target;
+    setCurrentConfig((prev) => ({
+      ...prev,
+      custom_http: {
+        ...(prev.custom_http ?? emptyCustomHttp),
+        [name]: value,
+      },
+    }));
+  };
+
+  const handleCommandChange = (
+    e: React.ChangeEvent<
+      HTMLInputElement | HTMLTextAreaElement | HTMLSelectElement
+    >,
+  ) => {
+    const { name, value } = e.target;
+    setCurrentConfig((prev) => ({
+      ...prev,
+      command: {
+        ...(prev.command ?? emptyCommand),
+        [name]:
+          name === "args"
+            ? value.split("\n")
+            : name === "env_passthrough"
+              ? value.trim() === ""
+                ? null
+                : value.split(",").map((v) => v.trim())
+              : value,
+      },
     }));
   };
 
@@ -50,7 +222,12 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
     ) {
       return "Configuration name must be unique";
     }
-    if (!config.api_key.trim()) return "API key is required";
+    if (
+      !config.api_key.trim() &&
+      !config.has_key &&
+      !keylessProviders.includes(config.provider)
+    )
+      return "API key is required";
     if (!config.model.trim()) return "Model name is required";
     if (config.temperature < 0 || config.temperature > 2)
       return "Temperature must be between 0 and 2";
@@ -67,12 +244,19 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
 
     setLoading(true);
     setError("");
+    setWarnings([]);
 
     try {
-      await invoke("register_llm", { config: currentConfig });
+      const result = await invoke<RegisterLlmResult>("register_llm", {
+        config: withoutBlankLines(withHeaders(currentConfig)),
+        requireTest,
+      });
+      setWarnings(result.warnings);
       // Reload configs
       await onUpdate();
       setCurrentConfig(defaultConfig);
+      setHeadersText("");
+      setExtraHeadersText("");
       setEditingIndex(null);
     } catch (error) {
       console.error("Error saving configuration:", error);
@@ -84,21 +268,72 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
     }
   };
 
+  // Send a probe request to check the key, model and endpoint work
+  const handleTestConfig = async (): Promise<void> => {
+    setTesting(true);
+    setTestResult(null);
+    try {
+      setTestResult(
+        await invoke<ConfigTestResult>("test_llm_config", {
+          config: withoutBlankLines(withHeaders(currentConfig)),
+        }),
+      );
+    } catch (error) {
+      setError(
+        typeof error === "string" ? error : "Failed to test configuration",
+      );
+    } finally {
+      setTesting(false);
+    }
+  };
+
+  // Ask the provider for its models so one can be picked instead of typed
+  const handleFetchModels = async (): Promise<void> => {
+    setLoadingModels(true);
+    setError("");
+    try {
+      setModels(
+        await invoke<ModelInfo[]>("list_models", {
+          config: withoutBlankLines(withHeaders(currentConfig)),
+        }),
+      );
+    } catch (error) {
+      console.error("Error listing models:", error);
+      setModels([]);
+      setError(typeof error === "string" ? error : "Failed to list models");
+    } finally {
+      setLoadingModels(false);
+    }
+  };
+
+  // Look for API keys in the environment and a local Ollama
+  const handleDiscoverProviders = async (): Promise<void> => {
+    setDiscovering(true);
+    setError("");
+    try {
+      setDiscoveryReport(await invoke<DiscoveryReport>("discover_providers"));
+      await onUpdate();
+    } catch (error) {
+      setError(
+        typeof error === "string" ? error : "Failed to detect providers",
+      );
+    } finally {
+      setDiscovering(false);
+    }
+  };
+
   const handleEditConfig = (index: number): void => {
     setCurrentConfig(configs[index]);
+    setHeadersText(formatHeaders(configs[index].custom_http?.headers));
+    setExtraHeadersText(formatHeaders(configs[index].headers));
     setEditingIndex(index);
     setError("");
   };
 
   const handleDeleteConfig = async (index: number): Promise<void> => {
     try {
-      // Create new array without the deleted config
-      const updatedConfigs = configs.filter((_, idx) => idx !== index);
-
-      // Save all remaining configs
-      for (const config of updatedConfigs) {
-        await invoke("register_llm", { config });
-      }
+      // Also removes the config's secrets from the vault
+      await invoke("delete_llm_config", { name: configs[index].name });
 
       // Reload configs
       await onUpdate();
@@ -117,6 +352,8 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
 
   const handleCancelEdit = (): void => {
     setCurrentConfig(defaultConfig);
+    setHeadersText("");
+    setExtraHeadersText("");
     setEditingIndex(null);
     setError("");
   };
@@ -150,6 +387,54 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
         </div>
       )}
 
+      {warnings.length > 0 && (
+        <div className="bg-yellow-50 text-yellow-700 p-3 rounded text-xs border border-yellow-200 mb-4">
+          {warnings.map((warning) => (
+            <div key={warning}>{warning}</div>
+          ))}
+        </div>
+      )}
+
+      <VaultPanel onUnlocked={onUpdate} />
+
+      <div className="mb-4">
+        <Button
+          onClick={handleDiscoverProviders}
+          isLoading={discovering}
+          variant="outline"
+          size="sm"
+          leftIcon={<Search className="h-3.5 w-3.5" />}
+        >
+          Detect Providers
+        </Button>
+        {discoveryReport && (
+          <div className="mt-2 bg-blue-50 text-blue-700 p-3 rounded text-xs border border-blue-200">
+            {discoveryReport.added.length === 0 &&
+              discoveryReport.failed.length === 0 &&
+              "No new providers found. Set OPENAI_API_KEY, ANTHROPIC_API_KEY or similar, or start Ollama."}
+            {discoveryReport.added.map((provider) => (
+              <div key={provider.name}>
+                Added "{provider.name}" ({provider.model}) from{" "}
+                {provider.source}
+              </div>
+            ))}
+            {discoveryReport.failed.map((provider) => (
+              <div key={provider.name}>
+                Skipped "{provider.name}" from {provider.source}, as it does
+                not work: {provider.error ?? "unknown error"}
+              </div>
+            ))}
+            {discoveryReport.default_provider &&
+              discoveryReport.updated_prompts > 0 && (
+                <div>
+                  {discoveryReport.updated_prompts} prompts now use "
+                  {discoveryReport.default_provider}"
+                </div>
+              )}
+          </div>
+        )}
+      </div>
+
       {/* Configuration Form */}
       <Card
         title={
@@ -181,6 +466,9 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
               <option value="phind">Phind</option>
               <option value="groq">Groq</option>
               <option value="google">Google</option>
+              <option value="custom_http">Custom HTTP</option>
+              <option value="command">Local Command</option>
+              <option value="mock">Mock (offline testing)</option>
             </Select>
           </div>
 
@@ -191,18 +479,274 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
               name="api_key"
               value={currentConfig.api_key}
               onChange={handleConfigChange}
-              placeholder="Enter API key"
+              placeholder={
+                currentConfig.has_key ? "Keep the saved key" : "Enter API key"
+              }
+              helperText="Or env:VAR_NAME, or file:~/path/to/key"
             />
+            <div>
+              <Input
+                label="Model"
+                type="text"
+                name="model"
+                list="model-options"
+                value={currentConfig.model}
+                onChange={handleConfigChange}
+                placeholder="e.g. gpt-4"
+              />
+              <datalist id="model-options">
+                {models.map((model) => (
+                  <option key={model.id} value={model.id}>
+                    {model.display_name}
+                  </option>
+                ))}
+              </datalist>
+              <button
+                type="button"
+                onClick={handleFetchModels}
+                disabled={loadingModels}
+                className="mt-1 text-xs text-blue-600 hover:underline disabled:text-gray-400"
+              >
+                {loadingModels
+                  ? "Fetching models..."
+                  : models.length > 0
+                    ? `${models.length} models available, refresh`
+                    : "Fetch available models"}
+              </button>
+            </div>
+          </div>
+
+          {!keylessProviders.includes(currentConfig.provider) && (
+            <>
+              <Textarea
+                label="Additional API Keys"
+                name="api_keys"
+                value={(currentConfig.api_keys ?? []).join("\n")}
+                onChange={(e) =>
+                  setCurrentConfig((prev) => ({
+                    ...prev,
+                    api_keys: e.target.value.split("\n"),
+                  }))
+                }
+                rows={2}
+                placeholder="e.g. env:OPENAI_API_KEY_2"
+                helperText="Optional. One per line; requests rotate between all keys to spread rate limits. Saved keys show as e.g. sk-…abcd #2; keep such lines as they are"
+              />
+              {(currentConfig.api_keys ?? []).some((key) => key.trim()) && (
+                <div className="grid grid-cols-2 gap-4">
+                  <Select
+                    label="Key Rotation"
+                    name="key_rotation"
+                    value={currentConfig.key_rotation ?? "round_robin"}
+                    onChange={handleConfigChange}
+                  >
+                    <option value="round_robin">Round robin</option>
+                    <option value="least_recently_limited">
+                      Least recently rate limited
+                    </option>
+                  </Select>
+                  <Input
+                    label="Key Cooldown (seconds)"
+                    type="number"
+                    name="key_cooldown_secs"
+                    value={currentConfig.key_cooldown_secs ?? ""}
+                    onChange={handleConfigChange}
+                    min="0"
+                    helperText="Skip a key after a 401 or 429, 60 by default"
+                  />
+                </div>
+              )}
+            </>
+          )}
+
+          {currentConfig.provider === "mock" && (
+            <div className="grid grid-cols-2 gap-4">
+              <Input
+                label="Fixtures File"
+                type="text"
+                name="fixtures_path"
+                value={currentConfig.mock?.fixtures_path ?? ""}
+                onChange={handleMockChange}
+                placeholder="e.g. ~/quillbert-fixtures.json"
+                helperText='For the "fixtures" model: JSON mapping prompt names to responses, "*" as default'
+              />
+              <Input
+                label="Latency (ms)"
+                type="number"
+                name="latency_ms"
+                min={0}
+                value={currentConfig.mock?.latency_ms ?? ""}
+                onChange={handleMockChange}
+                placeholder="None"
+              />
+              <Input
+                label="Fail Every N Requests"
+                type="number"
+                name="fail_every"
+                min={0}
+                value={currentConfig.mock?.fail_every ?? ""}
+                onChange={handleMockChange}
+                placeholder="Never"
+              />
+              <Input
+                label="Error Status"
+                type="number"
+                name="error_status"
+                value={currentConfig.mock?.error_status ?? ""}
+                onChange={handleMockChange}
+                placeholder="500"
+              />
+            </div>
+          )}
+
+          {currentConfig.provider === "command" && (
+            <div className="space-y-4">
+              <div className="grid grid-cols-2 gap-4">
+                <Input
+                  label="Program"
+                  type="text"
+                  name="program"
+                  value={currentConfig.command?.program ?? ""}
+                  onChange={handleCommandChange}
+                  placeholder="e.g. /usr/local/bin/llama-cli"
+                />
+                <Select
+                  label="Send Prompt Via"
+                  name="prompt_input"
+                  value={currentConfig.command?.prompt_input ?? "stdin"}
+                  onChange={handleCommandChange}
+                >
+                  <option value="stdin">Standard input</option>
+                  <option value="argument">Argument</option>
+                </Select>
+              </div>
+              <Textarea
+                label="Arguments"
+                name="args"
+                value={(currentConfig.command?.args ?? []).join("\n")}
+                onChange={handleCommandChange}
+                rows={3}
+                placeholder={"-m\n{{model}}"}
+                helperText="One per line. {{model}} is replaced by the model; with argument input, {{prompt}} by the prompt (or it is appended)"
+              />
+              <Input
+                label="Environment Passthrough"
+                type="text"
+                name="env_passthrough"
+                value={(currentConfig.command?.env_passthrough ?? []).join(", ")}
+                onChange={handleCommandChange}
+                placeholder="Whole environment"
+                helperText="Comma-separated variables to pass on; PATH and HOME are always passed"
+              />
+            </div>
+          )}
+
+          {currentConfig.provider === "custom_http" && (
+            <div className="space-y-4">
+              <Input
+                label="Endpoint URL"
+                type="text"
+                name="url"
+                value={currentConfig.custom_http?.url ?? ""}
+                onChange={handleCustomHttpChange}
+                placeholder="e.g. https://inference.internal/v1/generate"
+              />
+              <Textarea
+                label="Headers"
+                name="headers"
+                value={headersText}
+                onChange={(e) => {
+                  setHeadersText(e.target.value);
+                  setCurrentConfig((prev) => ({
+                    ...prev,
+                    custom_http: prev.custom_http ?? emptyCustomHttp,
+                  }));
+                }}
+                rows={2}
+                placeholder="Authorization: Bearer {{api_key}}"
+                helperText="One per line. {{api_key}} is replaced by the API key"
+              />
+              <Textarea
+                label="Request Body Template"
+                name="body_template"
+                value={
+                  currentConfig.custom_http?.body_template ??
+                  emptyCustomHttp.body_template
+                }
+                onChange={handleCustomHttpChange}
+                rows={3}
+                helperText="JSON with {{prompt}} and {{model}} placeholders in its strings"
+              />
+              <Input
+                label="Response Path"
+                type="text"
+                name="response_pointer"
+                value={currentConfig.custom_http?.response_pointer ?? ""}
+                onChange={handleCustomHttpChange}
+                placeholder="e.g. /output/0/text"
+                helperText="JSON pointer to the response text"
+              />
+            </div>
+          )}
+
+          <Input
+            label="Base URL"
+            type="text"
+            name="base_url"
+            value={currentConfig.base_url ?? ""}
+            onChange={handleConfigChange}
+            placeholder="e.g. http://localhost:8000/v1"
+            helperText="Optional. Use for self-hosted, OpenAI-compatible or remote Ollama endpoints"
+          />
+
+          <div className="grid grid-cols-2 gap-4">
             <Input
-              label="Model"
+              label="Proxy URL"
               type="text"
-              name="model"
-              value={currentConfig.model}
+              name="proxy_url"
+              value={currentConfig.proxy_url ?? ""}
               onChange={handleConfigChange}
-              placeholder="e.g. gpt-4"
+              placeholder="e.g. http://proxy.corp:8080"
+              helperText="Optional. HTTP, HTTPS or SOCKS5 proxy for this configuration"
+            />
+            <Input
+              label="No Proxy"
+              type="text"
+              name="no_proxy"
+              value={(currentConfig.no_proxy ?? []).join(", ")}
+              onChange={(e) =>
+                setCurrentConfig((prev) => ({
+                  ...prev,
+                  no_proxy: e.target.value
+                    .split(",")
+                    .map((host) => host.trim()),
+                }))
+              }
+              placeholder="e.g. localhost, .internal.corp"
+              helperText="Hosts reached directly, comma separated"
             />
           </div>
 
+          <Textarea
+            label="Extra Headers"
+            name="headers"
+            value={extraHeadersText}
+            onChange={(e) => setExtraHeadersText(e.target.value)}
+            rows={2}
+            placeholder="e.g. OpenAI-Organization: org-123"
+            helperText="Optional. One per line, sent with every request"
+          />
+
+          <Textarea
+            label="System Prompt"
+            name="system_prompt"
+            value={currentConfig.system_prompt ?? ""}
+            onChange={handleConfigChange}
+            rows={3}
+            placeholder="e.g. You are a concise technical writer."
+            helperText="Optional. Sent with every prompt that uses this configuration"
+          />
+
           <div className="grid grid-cols-2 gap-4">
             <Input
               label="Temperature"
@@ -226,6 +770,171 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
             />
           </div>
 
+          <div className="grid grid-cols-2 gap-4">
+            <Input
+              label="Timeout (seconds)"
+              type="number"
+              name="timeout_secs"
+              value={currentConfig.timeout_secs ?? ""}
+              onChange={handleConfigChange}
+              min="1"
+              helperText="Leave empty for no timeout"
+            />
+            <Input
+              label="Max Retries"
+              type="number"
+              name="max_retries"
+              value={currentConfig.max_retries ?? ""}
+              onChange={handleConfigChange}
+              min="0"
+              placeholder="2"
+              helperText="Retries on rate limits and server errors; 0 turns them off"
+            />
+          </div>
+
+          <div className="grid grid-cols-3 gap-4">
+            <Input
+              label="Requests / Minute"
+              type="number"
+              name="requests_per_minute"
+              value={currentConfig.requests_per_minute ?? ""}
+              onChange={handleConfigChange}
+              min="1"
+              helperText="Leave empty for no limit"
+            />
+            <Input
+              label="Tokens / Minute"
+              type="number"
+              name="tokens_per_minute"
+              value={currentConfig.tokens_per_minute ?? ""}
+              onChange={handleConfigChange}
+              min="1"
+              helperText="Prompt plus max tokens"
+            />
+            <Input
+              label="Max Concurrent"
+              type="number"
+              name="max_concurrent"
+              value={currentConfig.max_concurrent ?? ""}
+              onChange={handleConfigChange}
+              min="1"
+              helperText="Requests in flight at once"
+            />
+          </div>
+
+          <div className="grid grid-cols-3 gap-4">
+            <Input
+              label="Top P"
+              type="number"
+              name="top_p"
+              value={currentConfig.top_p ?? ""}
+              onChange={handleConfigChange}
+              step="0.05"
+              min="0"
+              max="1"
+            />
+            <Input
+              label="Top K"
+              type="number"
+              name="top_k"
+              value={currentConfig.top_k ?? ""}
+              onChange={handleConfigChange}
+              min="1"
+            />
+            <Input
+              label="Seed"
+              type="number"
+              name="seed"
+              value={currentConfig.seed ?? ""}
+              onChange={handleConfigChange}
+              min="0"
+              helperText="For deterministic output"
+            />
+          </div>
+
+          <div className="grid grid-cols-2 gap-4">
+            <Select
+              label="Reasoning Effort"
+              name="reasoning_effort"
+              value={currentConfig.reasoning_effort ?? ""}
+              onChange={handleConfigChange}
+              helperText="OpenAI-style reasoning models; temperature and top P are not sent while it is set"
+            >
+              <option value="">Default</option>
+              <option value="low">Low</option>
+              <option value="medium">Medium</option>
+              <option value="high">High</option>
+            </Select>
+            <Input
+              label="Thinking Budget"
+              type="number"
+              name="thinking_budget"
+              value={currentConfig.thinking_budget ?? ""}
+              onChange={handleConfigChange}
+              min="0"
+              helperText="Tokens for Anthropic and Google thinking (Anthropic: at least 1024 and below max tokens)"
+            />
+          </div>
+
+          <Textarea
+            label="Stop Sequences"
+            name="stop"
+            value={(currentConfig.stop ?? []).join("\n")}
+            onChange={(e) =>
+              setCurrentConfig((prev) => ({
+                ...prev,
+                stop: e.target.value.split("\n"),
+              }))
+            }
+            rows={2}
+            helperText="One per line. Leave empty to disable"
+          />
+
+          <Select
+            label="Streaming"
+            name="stream"
+            value={currentConfig.stream ? "true" : "false"}
+            onChange={handleConfigChange}
+            helperText="Show the response as it is generated (not supported by Phind)"
+          >
+            <option value="false">Disabled</option>
+            <option value="true">Enabled</option>
+          </Select>
+
+          {testResult && (
+            <div
+              className={`p-3 rounded text-xs border ${
+                testResult.success
+                  ? "bg-green-50 text-green-700 border-green-200"
+                  : "bg-red-50 text-red-500 border-red-200"
+              }`}
+            >
+              {testResult.success
+                ? `Test passed in ${testResult.latency_ms} ms. Model replied: "${testResult.response}"`
+                : `Test failed (${testResult.failure}) after ${testResult.latency_ms} ms: ${testResult.error}`}
+            </div>
+          )}
+
+          <div className="flex items-center justify-between">
+            <label className="flex items-center text-xs text-gray-600">
+              <input
+                type="checkbox"
+                checked={requireTest}
+                onChange={(e) => setRequireTest(e.target.checked)}
+                className="mr-2"
+              />
+              Require a passing test before saving
+            </label>
+            <Button
+              onClick={handleTestConfig}
+              isLoading={testing}
+              variant="outline"
+              size="sm"
+            >
+              Test Configuration
+            </Button>
+          </div>
+
           <Button
             onClick={handleSaveConfig}
             isLoading={loading}
@@ -261,6 +970,13 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
                 title={config.name}
                 provider={config.provider}
                 model={config.model}
+                badge={
+                  config.key_source
+                    ? config.api_keys?.length
+                      ? `${keySourceLabels[config.key_source]} +${config.api_keys.length}`
+                      : keySourceLabels[config.key_source]
+                    : undefined
+                }
                 config={{
                   temperature: config.temperature,
                   maxTokens: config.max_tokens,
@@ -290,6 +1006,8 @@ const LLMConfigurations: React.FC<LLMConfigurationsProps> = ({
           </div>
         </div>
       )}
+
+      <ContextWindows />
     </div>
   );
 };
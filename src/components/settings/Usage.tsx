@@ -0,0 +1,253 @@
+import React, { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Plus, Save, Trash2 } from "lucide-react";
+import { ModelPrice, UsageGroup, UsageReport } from "@/types";
+import Input from "@/components/ui/Input";
+import Button from "@/components/ui/Button";
+import Card from "@/components/ui/Card";
+
+const formatCost = (cost: number): string => `$${cost.toFixed(4)}`;
+
+const UsageTable: React.FC<{ title: string; groups: UsageGroup[] }> = ({
+  title,
+  groups,
+}) => (
+  <Card title={title} className="mb-4">
+    {groups.length === 0 ? (
+      <p className="text-xs text-gray-400 italic">No usage in this range.</p>
+    ) : (
+      <table className="w-full text-xs text-gray-700">
+        <thead>
+          <tr className="text-left text-gray-500">
+            <th className="py-1 font-medium">Name</th>
+            <th className="py-1 font-medium text-right">Requests</th>
+            <th className="py-1 font-medium text-right">Input</th>
+            <th className="py-1 font-medium text-right">Output</th>
+            <th className="py-1 font-medium text-right">Cost</th>
+          </tr>
+        </thead>
+        <tbody>
+          {groups.map((group) => (
+            <tr key={group.name} className="border-t border-gray-100">
+              <td className="py-1">{group.name}</td>
+              <td className="py-1 text-right">{group.requests}</td>
+              <td className="py-1 text-right">{group.input_tokens}</td>
+              <td className="py-1 text-right">{group.output_tokens}</td>
+              <td className="py-1 text-right">
+                {formatCost(group.cost)}
+                {group.unpriced_requests > 0 && "*"}
+              </td>
+            </tr>
+          ))}
+        </tbody>
+      </table>
+    )}
+  </Card>
+);
+
+const Usage: React.FC = () => {
+  const [from, setFrom] = useState<string>("");
+  const [to, setTo] = useState<string>("");
+  const [report, setReport] = useState<UsageReport | null>(null);
+  const [prices, setPrices] = useState<ModelPrice[]>([]);
+  const [error, setError] = useState<string>("");
+  const [saving, setSaving] = useState<boolean>(false);
+
+  const loadReport = useCallback(async () => {
+    try {
+      const range = { from: from || null, to: to || null };
+      setReport(await invoke<UsageReport>("get_usage_report", { range }));
+      setError("");
+    } catch (err) {
+      setError(typeof err === "string" ? err : "Failed to load usage report");
+    }
+  }, [from, to]);
+
+  useEffect(() => {
+    loadReport();
+  }, [loadReport]);
+
+  useEffect(() => {
+    invoke<ModelPrice[]>("get_model_prices")
+      .then(setPrices)
+      .catch((err) => console.error("Error loading model prices:", err));
+  }, []);
+
+  const updatePrice = (
+    index: number,
+    field: keyof ModelPrice,
+    value: string,
+  ) => {
+    setPrices((prev) =>
+      prev.map((price, idx) =>
+        idx === index
+          ? {
+              ...price,
+              [field]:
+                field === "input_per_million" || field === "output_per_million"
+                  ? Number(value)
+                  : value,
+            }
+          : price,
+      ),
+    );
+  };
+
+  const handleSavePrices = async () => {
+    setSaving(true);
+    try {
+      await invoke("update_model_prices", {
+        prices: prices.filter((p) => p.provider.trim() && p.model.trim()),
+      });
+      await loadReport();
+    } catch (err) {
+      setError(typeof err === "string" ? err : "Failed to save prices");
+    } finally {
+      setSaving(false);
+    }
+  };
+
+  return (
+    <div className="p-6">
+      <h2 className="text-lg font-medium text-gray-800 mb-4">Usage</h2>
+
+      <p className="text-sm text-gray-600 mb-6">
+        Tokens used and estimated cost per day, month, model and prompt. Costs
+        are calculated from the price table below; * marks totals that include
+        models without a price.
+      </p>
+
+      {error && (
+        <div className="bg-red-50 text-red-500 p-3 rounded text-xs border border-red-200 mb-4">
+          {error}
+        </div>
+      )}
+
+      <div className="grid grid-cols-2 gap-4 mb-4">
+        <Input
+          label="From"
+          type="date"
+          value={from}
+          onChange={(e) => setFrom(e.target.value)}
+        />
+        <Input
+          label="To"
+          type="date"
+          value={to}
+          onChange={(e) => setTo(e.target.value)}
+        />
+      </div>
+
+      {report && (
+        <>
+          <Card title="Total" className="mb-4">
+            <p className="text-xs text-gray-700">
+              {report.total.requests} requests, {report.total.input_tokens}{" "}
+              input and {report.total.output_tokens} output tokens,{" "}
+              {formatCost(report.total.cost)}
+              {report.total.estimated_requests > 0 &&
+                ` (${report.total.estimated_requests} requests estimated)`}
+            </p>
+          </Card>
+          <UsageTable title="By Prompt" groups={report.prompts} />
+          <UsageTable title="By Model" groups={report.models} />
+          <UsageTable title="Monthly" groups={report.monthly} />
+          <UsageTable title="Daily" groups={report.daily} />
+        </>
+      )}
+
+      <Card title="Prices (USD per million tokens)" className="mb-4">
+        <p className="text-xs text-gray-500 mb-3">
+          Models match by prefix, so "gpt-4o" also covers "gpt-4o-2024-08-06".
+          The longest match wins.
+        </p>
+        <div className="space-y-2">
+          {prices.map((price, index) => (
+            <div key={index} className="grid grid-cols-9 gap-2 items-center">
+              <div className="col-span-2">
+                <Input
+                  value={price.provider}
+                  onChange={(e) =>
+                    updatePrice(index, "provider", e.target.value)
+                  }
+                  placeholder="Provider"
+                />
+              </div>
+              <div className="col-span-3">
+                <Input
+                  value={price.model}
+                  onChange={(e) => updatePrice(index, "model", e.target.value)}
+                  placeholder="Model"
+                />
+              </div>
+              <div className="col-span-2">
+                <Input
+                  type="number"
+                  step="0.01"
+                  min="0"
+                  value={price.input_per_million}
+                  onChange={(e) =>
+                    updatePrice(index, "input_per_million", e.target.value)
+                  }
+                  placeholder="Input"
+                />
+              </div>
+              <div className="col-span-1">
+                <Input
+                  type="number"
+                  step="0.01"
+                  min="0"
+                  value={price.output_per_million}
+                  onChange={(e) =>
+                    updatePrice(index, "output_per_million", e.target.value)
+                  }
+                  placeholder="Output"
+                />
+              </div>
+              <button
+                onClick={() =>
+                  setPrices((prev) => prev.filter((_, idx) => idx !== index))
+                }
+                className="text-gray-400 hover:text-red-500"
+                title="Remove price"
+              >
+                <Trash2 className="h-4 w-4" />
+              </button>
+            </div>
+          ))}
+
+          <div className="flex gap-2">
+            <Button
+              onClick={() =>
+                setPrices((prev) => [
+                  ...prev,
+                  {
+                    provider: "",
+                    model: "",
+                    input_per_million: 0,
+                    output_per_million: 0,
+                  },
+                ])
+              }
+              variant="outline"
+              size="sm"
+              leftIcon={<Plus className="h-3.5 w-3.5" />}
+            >
+              Add Price
+            </Button>
+            <Button
+              onClick={handleSavePrices}
+              isLoading={saving}
+              size="sm"
+              leftIcon={<Save className="h-3.5 w-3.5" />}
+            >
+              Save Prices
+            </Button>
+          </div>
+        </div>
+      </Card>
+    </div>
+  );
+};
+
+export default Usage;
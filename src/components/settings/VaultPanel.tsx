@@ -0,0 +1,116 @@
+import React, { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { Lock, Unlock } from "lucide-react";
+import { VaultStatus } from "@/types";
+import Input from "@/components/ui/Input";
+import Button from "@/components/ui/Button";
+import Card from "@/components/ui/Card";
+
+interface VaultPanelProps {
+  onUnlocked: () => Promise<void>;
+}
+
+// Unlocks the encrypted store that holds API keys, creating it on first use
+const VaultPanel: React.FC<VaultPanelProps> = ({ onUnlocked }) => {
+  const [status, setStatus] = useState<VaultStatus | null>(null);
+  const [passphrase, setPassphrase] = useState<string>("");
+  const [error, setError] = useState<string>("");
+  const [loading, setLoading] = useState<boolean>(false);
+
+  const loadStatus = useCallback(async () => {
+    try {
+      setStatus(await invoke<VaultStatus>("get_vault_status"));
+    } catch (err) {
+      console.error("Error loading vault status:", err);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadStatus();
+  }, [loadStatus]);
+
+  const handleUnlock = async () => {
+    setLoading(true);
+    setError("");
+    try {
+      await invoke("unlock_vault", { passphrase });
+      setPassphrase("");
+      await loadStatus();
+      // Plaintext keys were moved into the vault, so reload the configs
+      await onUnlocked();
+    } catch (err) {
+      setError(typeof err === "string" ? err : "Failed to unlock the vault");
+    } finally {
+      setLoading(false);
+    }
+  };
+
+  const handleLock = async () => {
+    try {
+      await invoke("lock_vault");
+      await loadStatus();
+    } catch (err) {
+      setError(typeof err === "string" ? err : "Failed to lock the vault");
+    }
+  };
+
+  if (!status) return null;
+
+  if (status.unlocked) {
+    return (
+      <Card className="mb-6">
+        <div className="flex items-center justify-between">
+          <span className="flex items-center text-xs text-gray-600">
+            <Unlock className="h-3.5 w-3.5 mr-2 text-green-600" />
+            Secrets vault unlocked. API keys are stored encrypted.
+          </span>
+          <Button onClick={handleLock} variant="ghost" size="sm">
+            Lock
+          </Button>
+        </div>
+      </Card>
+    );
+  }
+
+  return (
+    <Card
+      title={status.exists ? "Unlock Secrets Vault" : "Create Secrets Vault"}
+      icon={<Lock className="h-4 w-4" />}
+      className="mb-6"
+    >
+      <p className="text-xs text-gray-600 mb-3">
+        {status.exists
+          ? "Enter your passphrase to use the API keys stored in the vault."
+          : "Choose a passphrase to encrypt your API keys. Keys currently saved in plain text will be moved into the vault."}
+      </p>
+      {status.plaintext_keys > 0 && (
+        <p className="text-xs text-amber-600 mb-3">
+          {status.plaintext_keys === 1
+            ? "1 API key is"
+            : `${status.plaintext_keys} API keys are`}{" "}
+          saved in plain text and will be encrypted once the vault is{" "}
+          {status.exists ? "unlocked" : "created"}.
+        </p>
+      )}
+      {error && <p className="text-xs text-red-500 mb-2">{error}</p>}
+      <div className="flex gap-2 items-start">
+        <Input
+          type="password"
+          value={passphrase}
+          onChange={(e) => setPassphrase(e.target.value)}
+          onKeyDown={(e) => e.key === "Enter" && handleUnlock()}
+          placeholder="Passphrase"
+        />
+        <Button
+          onClick={handleUnlock}
+          isLoading={loading}
+          disabled={passphrase === ""}
+        >
+          {status.exists ? "Unlock" : "Create"}
+        </Button>
+      </div>
+    </Card>
+  );
+};
+
+export default VaultPanel;
@@ -8,10 +8,19 @@ export interface CustomPrompt {
 export interface CustomPromptConfig {
   name: string;
   provider_name: string;
+  fallback_providers?: string[];
+  system_prompt?: string | null;
+  cache_ttl_secs?: number | null;
+  chunking?: ChunkingConfig | null;
   prompt_template: string;
   shortcut: string;
 }
 
+export interface ChunkingConfig {
+  chunk_tokens: number;
+  reduce_prompt?: string | null;
+}
+
 export interface ShortcutConfig {
   name: string;
   shortcut: string;
@@ -22,12 +31,101 @@ export interface LLMConfig {
   name: string;
   provider: LLMProvider;
   api_key: string;
+  api_keys?: string[];
+  key_rotation?: "round_robin" | "least_recently_limited";
+  key_cooldown_secs?: number | null;
   model: string;
   temperature: number;
   max_tokens: number;
+  base_url?: string | null;
+  system_prompt?: string | null;
+  stream?: boolean;
+  timeout_secs?: number | null;
+  max_retries?: number | null;
+  retry_backoff_ms?: number | null;
+  retry_backoff_max_ms?: number | null;
+  top_p?: number | null;
+  top_k?: number | null;
+  stop?: string[];
+  seed?: number | null;
+  reasoning_effort?: "low" | "medium" | "high" | null;
+  thinking_budget?: number | null;
+  has_key?: boolean;
+  key_source?: KeySource;
+  mock?: MockConfig | null;
+  custom_http?: CustomHttpConfig | null;
+  command?: CommandConfig | null;
+  proxy_url?: string | null;
+  no_proxy?: string[];
+  headers?: Record<string, string>;
+  requests_per_minute?: number | null;
+  tokens_per_minute?: number | null;
+  max_concurrent?: number | null;
+}
+
+export interface CommandConfig {
+  program: string;
+  args?: string[];
+  prompt_input?: "stdin" | "argument";
+  env_passthrough?: string[] | null;
+}
+
+export interface CustomHttpConfig {
+  url: string;
+  headers?: Record<string, string>;
+  body_template: string;
+  response_pointer: string;
+}
+
+export interface MockConfig {
+  fixtures_path?: string | null;
+  latency_ms?: number | null;
+  fail_every?: number | null;
+  error_status?: number | null;
+}
+
+export type KeySource = "none" | "plaintext" | "vault" | "env" | "file";
+
+export interface ConfigTestResult {
+  success: boolean;
+  latency_ms: number;
+  response?: string | null;
+  failure?: "auth" | "unknown_model" | "network" | "quota" | "other" | null;
+  error?: string | null;
+}
+
+export interface ModelInfo {
+  id: string;
+  display_name?: string;
+  owned_by?: string;
+  context_window?: number;
+  size_bytes?: number;
+}
+
+export interface DiscoveredProvider {
+  name: string;
+  provider: LLMProvider;
+  model: string;
+  source: string;
+  error?: string | null;
+}
+
+export interface DiscoveryReport {
+  added: DiscoveredProvider[];
+  failed: DiscoveredProvider[];
+  default_provider?: string | null;
+  updated_prompts: number;
+}
+
+export interface RegisterLlmResult {
+  message: string;
+  warnings: string[];
 }
 
 export type LLMProvider =
+  | "mock"
+  | "custom_http"
+  | "command"
   | "openai"
   | "anthropic"
   | "ollama"
@@ -38,8 +136,115 @@ export type LLMProvider =
   | "google";
 
 export interface PromptResponse {
+  job_id?: number;
   prompt_name: string;
   response: string;
+  provider_name?: string;
+  cached?: boolean;
+}
+
+export interface PromptResponseChunk {
+  job_id: number;
+  prompt_name: string;
+  sequence: number;
+  chunk: string;
+}
+
+export interface PromptRetry {
+  job_id: number;
+  prompt_name: string;
+  attempt: number;
+  max_retries: number;
+  delay_ms: number;
+  error: string;
+}
+
+export interface PromptFallback {
+  job_id: number;
+  prompt_name: string;
+  failed_provider: string;
+  error: string;
+  next_provider: string;
+}
+
+export interface PromptProgress {
+  job_id: number;
+  prompt_name: string;
+  stage: "map" | "reduce";
+  completed: number;
+  total: number;
+}
+
+export interface PromptQueued {
+  job_id: number;
+  prompt_name: string;
+  provider_name: string;
+  limit: "requests_per_minute" | "tokens_per_minute" | "concurrency";
+  wait_ms?: number | null;
+}
+
+export interface PromptPreflight {
+  prompt_name: string;
+  message: string;
+  // Set for a follow-up, which is sent again once confirmed
+  job_id?: number;
+  follow_up?: string;
+  provider_name: string;
+  model: string;
+  prompt_tokens: number;
+  max_tokens: number;
+  context_window: number;
+}
+
+export interface ContextWindow {
+  provider: string;
+  model: string;
+  tokens: number;
+}
+
+export interface ProviderGroup {
+  name: string;
+  providers: string[];
+}
+
+export interface PromptJob {
+  job_id: number;
+  prompt_name: string;
+}
+
+export interface ModelPrice {
+  provider: string;
+  model: string;
+  input_per_million: number;
+  output_per_million: number;
+}
+
+export interface UsageTotals {
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  estimated_requests: number;
+  cost: number;
+  unpriced_requests: number;
+}
+
+export interface UsageGroup extends UsageTotals {
+  name: string;
+}
+
+export interface UsageReport {
+  total: UsageTotals;
+  daily: UsageGroup[];
+  monthly: UsageGroup[];
+  models: UsageGroup[];
+  prompts: UsageGroup[];
+}
+
+export interface VaultStatus {
+  exists: boolean;
+  unlocked: boolean;
+  // API keys still saved in plain text in settings.json
+  plaintext_keys: number;
 }
 
 export interface NotificationStatus {